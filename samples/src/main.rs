@@ -1,12 +1,16 @@
+use std::fmt::Debug;
 use std::path::Path;
+use std::process::exit;
 
 use clap::{Parser, ValueEnum};
-use pdf_extract::extract_text;
 
 use bank_statement_parser::bank_of_america_credit_statement::BankOfAmericaCreditStatement;
 use bank_statement_parser::bank_of_america_debit_statement::BankOfAmericaDebitStatement;
 use bank_statement_parser::chase_credit_statement::ChaseCreditStatement;
-use bank_statement_parser::statement_format::StatementFormat;
+use bank_statement_parser::detect::parse_any_inspect;
+use bank_statement_parser::error::StatementError;
+use bank_statement_parser::extract::Inspect;
+use bank_statement_parser::statement_format::{ParseOptions, StatementFormat};
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
 enum StatementType {
@@ -19,32 +23,44 @@ enum StatementType {
 #[command(author, version, about)]
 struct Args {
     filename: String,
+    /// Statement type; detected from the statement text if omitted.
     #[arg(value_enum, short)]
-    type_: StatementType,
+    type_: Option<StatementType>,
     #[arg(short, long)]
     verbose: bool,
 }
 
+fn print_result<T: Debug>(result: Result<T, StatementError>) {
+    match result {
+        Ok(statement) => println!("{:?}", statement),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+fn parse<T: StatementFormat + Debug>(path: &Path, verbose: bool) {
+    let extractor = Inspect::new(T::preferred_extractor(), |text| {
+        if verbose {
+            println!("{}", text);
+        }
+    });
+    print_result(T::parse_file_with(path, &extractor));
+}
+
 fn main() {
     let args = Args::parse();
     let path = Path::new(&args.filename);
-    if args.verbose {
-        let pdf_text = extract_text(&path).unwrap();
-        println!("{}", pdf_text);
-    }
 
     match args.type_ {
-        StatementType::BoaCredit => {
-            let statement = BankOfAmericaCreditStatement::parse_file(&path);
-            println!("{:?}", statement);
-        },
-        StatementType::BoaDebit => {
-            let statement = BankOfAmericaDebitStatement::parse_file(&path);
-            println!("{:?}", statement);
-        },
-        StatementType::ChaseCredit => {
-            let statement = ChaseCreditStatement::parse_file(&path);
-            println!("{:?}", statement);
-        },
+        None => print_result(parse_any_inspect(path, &ParseOptions::default(), |text| {
+            if args.verbose {
+                println!("{}", text);
+            }
+        })),
+        Some(StatementType::BoaCredit) => parse::<BankOfAmericaCreditStatement>(path, args.verbose),
+        Some(StatementType::BoaDebit) => parse::<BankOfAmericaDebitStatement>(path, args.verbose),
+        Some(StatementType::ChaseCredit) => parse::<ChaseCreditStatement>(path, args.verbose),
     }
 }
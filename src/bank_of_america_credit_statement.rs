@@ -1,64 +1,96 @@
-use std::path::Path;
-
 use chrono::naive::NaiveDate as Date;
 use nom::{
     bytes::complete::{is_a, tag},
-    character::complete::{anychar, digit1, i32, multispace0, multispace1},
-    combinator::{map, map_opt, opt, peek},
-    error::{Error, ErrorKind},
+    character::complete::{anychar, digit1, multispace0, multispace1, space0},
+    combinator::{map, opt, peek},
+    error::{context, ErrorKind, ParseError},
     multi::{many1, many_till},
     sequence::{delimited, preceded, separated_pair, terminated},
-    IResult,
 };
-use pdf_extract::extract_text;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::common_parsers::*;
-use crate::statement_format::StatementFormat;
+use crate::credit_terms::{apr_table, CreditTerms};
+use crate::detect::Format;
+use crate::error::{
+    finish, verify_total, GrammarError, PResult, ReconciledTransaction, StatementError,
+};
+use crate::extract::TextExtractor;
+use crate::foreign_exchange::{foreign_exchange, foreign_transaction_fees, ForeignExchange};
+use crate::money::Money;
+use crate::rewards::{rewards_summary, RewardsSummary};
+use crate::statement::{
+    self, AccountKind, FeeKind, Issuer, RunningBalance, Statement, TransactionKind,
+};
+use crate::statement_format::{ParseOptions, StatementFormat};
+use crate::year_to_date::{year_to_date, YearToDate};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TransactionType {
     Credit,
     Purchase,
     Fee,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
     pub type_: TransactionType,
+    /// What the fee was for, for `TransactionType::Fee` transactions.
+    pub fee_kind: Option<FeeKind>,
     pub date: Date,
     pub posting_date: Date,
     pub description: String,
     pub reference_number: String,
     pub account_number: String,
-    pub amount: i32,
+    pub amount: Money,
+    /// The exchange details, for purchases made in another currency.
+    pub foreign_exchange: Option<ForeignExchange>,
+    /// Index in the statement's `transactions` of the foreign transaction fee
+    /// charged for this transaction.
+    pub foreign_transaction_fee: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BankOfAmericaCreditStatement {
     pub account_number: String,
     pub start_date: Date,
     pub end_date: Date,
-    pub start_balance: i32,
-    pub end_balance: i32,
+    pub start_balance: Money,
+    pub end_balance: Money,
     pub transactions: Vec<Transaction>,
-    pub total_interest: i32,
+    pub total_interest: Money,
+    pub credit_terms: CreditTerms,
+    pub year_to_date: Option<YearToDate>,
+    pub rewards: Option<RewardsSummary>,
+}
+
+impl From<&Transaction> for ReconciledTransaction {
+    fn from(transaction: &Transaction) -> Self {
+        ReconciledTransaction {
+            date: transaction.date,
+            description: transaction.description.clone(),
+            amount: transaction.amount,
+        }
+    }
 }
 
-fn account_number(input: &str) -> IResult<&str, String> {
-    map(is_a("0123456789 "), |x: &str| x.to_string())(input)
+fn account_number(input: &str) -> PResult<'_, String> {
+    map(is_a("0123456789 "), |x: &str| x.trim_end().to_string())(input)
 }
 
 fn transaction(
-    start_date: Date,
+    period: StatementPeriod,
     account_number: &str,
     transaction_type: TransactionType,
-) -> impl Fn(&str) -> IResult<&str, Transaction> + '_ {
+) -> impl Fn(&str) -> PResult<'_, Transaction> + '_ {
     move |input| {
-        let (input, date) =
-            map_opt(month_day, |(month, day)| infer_year(month, day, start_date))(input)?;
+        let (input, date) = month_day_in(period)(input)?;
         let (input, _) = multispace1(input)?;
-        let (input, posting_date) =
-            map_opt(month_day, |(month, day)| infer_year(month, day, start_date))(input)?;
+        let (input, posting_date) = month_day_in(period)(input)?;
         let (input, _) = multispace1(input)?;
         let (input, (description_chars, (reference_number, account_number))) = many_till(
             anychar,
@@ -68,17 +100,27 @@ fn transaction(
             ),
         )(input)?;
         let (input, amount) = preceded(multispace1, dollar_amount)(input)?;
-        let (input, _) = tag("\n\n")(input)?;
+        let (input, _) = tag("\n")(input)?;
+        let (input, exchange) = opt(terminated(
+            preceded(space0, foreign_exchange),
+            preceded(space0, tag("\n")),
+        ))(input)?;
+        let (input, _) = tag("\n")(input)?;
+        let description: String = description_chars.into_iter().collect();
         Ok((
             input,
             Transaction {
                 type_: transaction_type,
+                fee_kind: (transaction_type == TransactionType::Fee)
+                    .then(|| FeeKind::classify(&description)),
                 date,
                 posting_date,
-                description: description_chars.into_iter().collect(),
+                description,
                 reference_number: reference_number.into(),
                 account_number: account_number.into(),
                 amount,
+                foreign_exchange: exchange,
+                foreign_transaction_fee: None,
             },
         ))
     }
@@ -86,92 +128,192 @@ fn transaction(
 
 fn transaction_section<'a>(
     input: &'a str,
-    start_date: Date,
+    period: StatementPeriod,
     account_number: &str,
-    section_header: &str,
+    section_header: &'static str,
     transaction_type: TransactionType,
-) -> IResult<&'a str, Vec<Transaction>> {
-    let (input, ()) = take_until_including(section_header)(input)?;
-    let (input, transactions) =
-        many1(transaction(start_date, account_number, transaction_type))(input)?;
-    let (input, total) = preceded(
-        terminated(take_until_including("FOR THIS PERIOD"), multispace1),
-        dollar_amount,
-    )(input)?;
-    let (input, _) = tag("\n\n")(input)?;
-    // Check the total
-    let computed_total: i32 = transactions.iter().map(|t| t.amount).sum();
-    if computed_total != total {
-        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
-    }
-    Ok((input, transactions))
+) -> PResult<'a, Vec<Transaction>> {
+    context(section_header, move |input| {
+        let (input, ()) = take_until_including(section_header)(input)?;
+        let (input, transactions) =
+            many1(transaction(period, account_number, transaction_type))(input)?;
+        let (input, total) = preceded(
+            terminated(take_until_including("FOR THIS PERIOD"), multispace1),
+            dollar_amount,
+        )(input)?;
+        let (input, _) = tag("\n\n")(input)?;
+        // Check the total
+        let computed_total = sum_amounts(input, transactions.iter().map(|t| t.amount))?;
+        verify_total(input, section_header, total, computed_total, || {
+            transactions
+                .iter()
+                .map(ReconciledTransaction::from)
+                .collect()
+        })?;
+        Ok((input, transactions))
+    })(input)
+}
+
+fn credit_terms(input: &str) -> Result<CreditTerms, nom::Err<GrammarError<'_>>> {
+    Ok(CreditTerms {
+        payment_due_date: labeled(input, "Payment Due Date", month_day_year)?,
+        minimum_payment: labeled(input, "Total Minimum Payment Due", dollar_amount)?,
+        credit_line: labeled(input, "Total Credit Line", whole_dollar_amount)?,
+        available_credit: labeled(input, "Total Credit Available", whole_dollar_amount)?,
+        cash_access_line: labeled(input, "Cash Credit Line", whole_dollar_amount)?,
+        late_fee: labeled(input, "late fee of up to", whole_dollar_amount)?,
+        aprs: apr_table(input, "Interest Charge Calculation")?,
+    })
+}
+
+struct AccountSummary {
+    account_number: String,
+    start_date: Date,
+    end_date: Date,
+    start_balance: Money,
+    end_balance: Money,
 }
 
-fn parse_statement(input: &str) -> IResult<&str, BankOfAmericaCreditStatement> {
+fn account_summary(input: &str) -> PResult<'_, AccountSummary> {
     let (input, ()) = take_until_including("Account# ")(input)?;
     let (input, account_number) = account_number(input)?;
     let (input, _) = multispace0(input)?;
+    let period = input;
     let (input, ((start_month, start_day), (end_month, end_day))) =
         separated_pair(month_word_day, tag(" - "), month_word_day)(input)?;
-    let (input, end_year) = delimited(tag(", "), i32, multispace0)(input)?;
+    let (input, end_year) = delimited(tag(", "), year, multispace0)(input)?;
 
     let start_year = if start_month.number_from_month() > end_month.number_from_month() {
         end_year - 1
     } else {
         end_year
     };
-    let start_date =
-        Date::from_ymd_opt(start_year, start_month.number_from_month(), start_day).unwrap();
-    let end_date = Date::from_ymd_opt(end_year, end_month.number_from_month(), end_day).unwrap();
+    let dates = Date::from_ymd_opt(start_year, start_month.number_from_month(), start_day).zip(
+        Date::from_ymd_opt(end_year, end_month.number_from_month(), end_day),
+    );
+    let Some((start_date, end_date)) = dates else {
+        return Err(nom::Err::Failure(GrammarError::from_error_kind(
+            period,
+            ErrorKind::Verify,
+        )));
+    };
 
     let (input, ()) = take_until_including("Previous Balance ")(input)?;
     let (input, start_balance) = dollar_amount(input)?;
     let (input, ()) = take_until_including("New Balance Total ")(input)?;
     let (input, end_balance) = dollar_amount(input)?;
+    Ok((
+        input,
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+        },
+    ))
+}
 
-    let (input, mut transactions) = transaction_section(
+fn parse_statement<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> PResult<'a, BankOfAmericaCreditStatement> {
+    let credit_terms = credit_terms(input)?;
+    let year_to_date = year_to_date(input)?;
+    let rewards = rewards_summary(input, &["Rewards Summary", "Reward Summary"]);
+    let (
         input,
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+        },
+    ) = context("Account Summary", account_summary)(input)?;
+    let period = StatementPeriod {
         start_date,
-        &account_number[account_number.len() - 4..],
+        end_date,
+        tolerance: options.date_tolerance,
+    };
+
+    let (input, mut transactions) = transaction_section(
+        input,
+        period,
+        &account_number[account_number.len().saturating_sub(4)..],
         "Payments and Other Credits\n\n",
         TransactionType::Credit,
     )?;
 
     let (input, purchases) = transaction_section(
         input,
-        start_date,
-        &account_number[account_number.len() - 4..],
+        period,
+        &account_number[account_number.len().saturating_sub(4)..],
         "Purchases and Adjustments\n\n",
         TransactionType::Purchase,
     )?;
-    transactions.extend(purchases.into_iter());
+    transactions.extend(purchases);
 
     let (input, fees_present) = peek(opt(tag("Fees\n\n")))(input)?;
     let (input, fees) = if fees_present.is_some() {
         transaction_section(
             input,
-            start_date,
-            &account_number[account_number.len() - 4..],
+            period,
+            &account_number[account_number.len().saturating_sub(4)..],
             "Fees\n\n",
             TransactionType::Fee,
         )?
     } else {
         (input, Vec::new())
     };
-    transactions.extend(fees.into_iter());
+    transactions.extend(fees);
+
+    let fees = foreign_transaction_fees(
+        &transactions,
+        |t| t.posting_date,
+        |t| t.foreign_exchange.is_some(),
+        |t| t.fee_kind == Some(FeeKind::ForeignTransaction),
+    );
+    for (transaction, fee) in transactions.iter_mut().zip(fees) {
+        transaction.foreign_transaction_fee = fee;
+    }
 
-    let (input, total_interest) = preceded(
-        terminated(
-            take_until_including("TOTAL INTEREST CHARGED FOR THIS PERIOD"),
-            multispace1,
+    let (input, total_interest) = context(
+        "Interest Charged",
+        preceded(
+            terminated(
+                take_until_including("TOTAL INTEREST CHARGED FOR THIS PERIOD"),
+                multispace1,
+            ),
+            dollar_amount,
         ),
-        dollar_amount,
     )(input)?;
 
-    let computed_total = transactions.iter().map(|t| t.amount).sum::<i32>() + total_interest;
-    if end_balance - start_balance != computed_total {
-        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
-    }
+    let computed_total = sum_amounts(
+        input,
+        transactions
+            .iter()
+            .map(|t| t.amount)
+            .chain([total_interest]),
+    )?;
+    verify_total(
+        input,
+        "Account Summary",
+        checked(input, end_balance.checked_sub(start_balance))?,
+        computed_total,
+        || {
+            let mut reconciled: Vec<_> = transactions
+                .iter()
+                .map(ReconciledTransaction::from)
+                .collect();
+            reconciled.push(ReconciledTransaction {
+                date: end_date,
+                description: "TOTAL INTEREST CHARGED FOR THIS PERIOD".into(),
+                amount: total_interest,
+            });
+            reconciled
+        },
+    )?;
 
     Ok((
         input,
@@ -183,14 +325,142 @@ fn parse_statement(input: &str) -> IResult<&str, BankOfAmericaCreditStatement> {
             end_balance,
             transactions,
             total_interest,
+            credit_terms,
+            year_to_date,
+            rewards,
         },
     ))
 }
 
+impl BankOfAmericaCreditStatement {
+    /// The balance after each transaction; see [`statement::running_balances`].
+    pub fn running_balances(&self) -> Option<Vec<RunningBalance<'_, Transaction>>> {
+        statement::running_balances(
+            self.start_balance,
+            &self.transactions,
+            |t| t.posting_date,
+            |t| t.amount,
+        )
+    }
+}
+
 impl StatementFormat for BankOfAmericaCreditStatement {
-    fn parse_file(path: &Path) -> Self {
-        let pdf_text = extract_text(&path).unwrap();
-        let (_, statement) = parse_statement(pdf_text.as_str()).unwrap();
-        statement
+    fn parse_text_with_options(text: &str, options: &ParseOptions) -> Result<Self, StatementError> {
+        finish(text, parse_statement(text, options))
+    }
+
+    fn preferred_extractor() -> Box<dyn TextExtractor> {
+        Format::BankOfAmericaCredit.preferred_extractor()
+    }
+}
+
+impl From<TransactionType> for TransactionKind {
+    fn from(transaction_type: TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Credit => TransactionKind::Credit,
+            TransactionType::Purchase => TransactionKind::Purchase,
+            TransactionType::Fee => TransactionKind::Fee,
+        }
+    }
+}
+
+impl From<Transaction> for statement::Transaction {
+    fn from(transaction: Transaction) -> Self {
+        statement::Transaction {
+            kind: transaction.type_.into(),
+            fee_kind: transaction.fee_kind,
+            date: transaction.date,
+            posting_date: Some(transaction.posting_date),
+            description: transaction.description,
+            reference_number: Some(transaction.reference_number),
+            check_number: None,
+            section: None,
+            card_number: Some(transaction.account_number),
+            amount: AccountKind::Credit.normalize(transaction.amount),
+            printed_amount: transaction.amount,
+            foreign_exchange: transaction.foreign_exchange,
+            foreign_transaction_fee: transaction.foreign_transaction_fee,
+        }
+    }
+}
+
+impl From<BankOfAmericaCreditStatement> for Statement {
+    fn from(statement: BankOfAmericaCreditStatement) -> Self {
+        Statement {
+            issuer: Issuer::BankOfAmerica,
+            account_kind: AccountKind::Credit,
+            account_number: statement.account_number,
+            start_date: statement.start_date,
+            end_date: statement.end_date,
+            start_balance: statement.start_balance,
+            end_balance: statement.end_balance,
+            total_interest: Some(statement.total_interest),
+            credit_terms: Some(statement.credit_terms),
+            year_to_date: statement.year_to_date,
+            rewards: statement.rewards,
+            daily_balances: None,
+            transactions: statement
+                .transactions
+                .into_iter()
+                .map(statement::Transaction::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = "\
+Bank of America
+Account# 4400 1234 5678 9012
+December 15 - January 14, 2023
+Payment Due Date 02/11/2023
+Total Minimum Payment Due $35.00
+Late Payment Warning: If we do not receive your minimum payment by the date listed above, you may have to pay a late fee of up to $40 and your APRs may be increased up to the Penalty APR of 29.99%.
+Previous Balance $500.00
+New Balance Total $552.00
+Total Credit Line $8,000.00
+Total Credit Available $7,448.00
+Cash Credit Line $2,400.00
+Payments and Other Credits
+
+01/05 01/05 PAYMENT - THANK YOU 1111 9012 -500.00
+
+TOTAL PAYMENTS AND OTHER CREDITS FOR THIS PERIOD -$500.00
+
+Purchases and Adjustments
+
+12/20 12/21 GROCERY STORE 2222 9012 528.00
+
+12/22 12/23 CAFE LONDON 3333 9012 11.88
+THAI BAHT 700.00 X 0.0285 (EXCHG RATE)
+
+TOTAL PURCHASES AND ADJUSTMENTS FOR THIS PERIOD $539.88
+
+Fees
+
+12/22 12/23 FOREIGN TRANSACTION FEE 4444 9012 0.12
+
+TOTAL FEES FOR THIS PERIOD $0.12
+
+Interest Charged
+TOTAL INTEREST CHARGED FOR THIS PERIOD $12.00
+";
+
+    #[test]
+    fn links_exchange_line_in_any_currency() {
+        let statement = BankOfAmericaCreditStatement::parse_text(STATEMENT).unwrap();
+        let cafe = &statement.transactions[2];
+        let exchange = cafe.foreign_exchange.as_ref().unwrap();
+        assert_eq!(exchange.currency, "THAI BAHT");
+        assert_eq!(exchange.currency_code, None);
+        assert_eq!(exchange.original_cents, 70000);
+        assert_eq!(cafe.foreign_transaction_fee, Some(3));
+        assert_eq!(
+            statement.transactions[3].fee_kind,
+            Some(FeeKind::ForeignTransaction)
+        );
     }
 }
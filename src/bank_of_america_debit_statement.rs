@@ -1,49 +1,138 @@
-use std::path::Path;
-
-use chrono::naive::NaiveDate as Date;
+use chrono::{naive::NaiveDate as Date, Duration};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
-    character::complete::{anychar, digit1, multispace0, multispace1},
-    combinator::{opt, peek, recognize},
-    error::{Error, ErrorKind},
+    bytes::complete::{tag, take},
+    character::complete::{anychar, char, digit1, multispace0, multispace1},
+    combinator::{map_res, opt, peek, recognize},
+    error::context,
     multi::{many0, many1_count, many_till},
-    sequence::{delimited, preceded},
-    IResult,
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
 };
-use pdf_extract::extract_text;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::common_parsers::*;
-use crate::statement_format::StatementFormat;
+use crate::detect::Format;
+use crate::error::{
+    finish, verify_balance_on, verify_total, GrammarError, PResult, ReconciledTransaction,
+    StatementError,
+};
+use crate::extract::TextExtractor;
+use crate::money::Money;
+use crate::statement::{
+    self, AccountKind, FeeKind, Issuer, RunningBalance, Statement, TransactionKind,
+};
+use crate::statement_format::{ParseOptions, StatementFormat};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TransactionType {
     Deposit,
     Withdrawal,
     Fee,
 }
 
-#[derive(Debug)]
+/// The sections of the statement that list transactions. Some accounts get
+/// a single "Withdrawals and other subtractions" section, others get it
+/// broken down into ATM and debit card, other, and check subtractions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Section {
+    DepositsAndOtherAdditions,
+    WithdrawalsAndOtherSubtractions,
+    AtmAndDebitCardSubtractions,
+    OtherSubtractions,
+    Checks,
+    ServiceFees,
+}
+
+impl Section {
+    /// Every section, in the order Bank of America prints them.
+    const ALL: [Section; 6] = [
+        Section::DepositsAndOtherAdditions,
+        Section::WithdrawalsAndOtherSubtractions,
+        Section::AtmAndDebitCardSubtractions,
+        Section::OtherSubtractions,
+        Section::Checks,
+        Section::ServiceFees,
+    ];
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Section::DepositsAndOtherAdditions => "Deposits and other additions",
+            Section::WithdrawalsAndOtherSubtractions => "Withdrawals and other subtractions",
+            Section::AtmAndDebitCardSubtractions => "ATM and debit card subtractions",
+            Section::OtherSubtractions => "Other subtractions",
+            Section::Checks => "Checks",
+            Section::ServiceFees => "Service fees",
+        }
+    }
+
+    fn footer(self) -> &'static str {
+        match self {
+            Section::DepositsAndOtherAdditions => "Total deposits and other additions",
+            Section::WithdrawalsAndOtherSubtractions => "Total withdrawals and other subtractions",
+            Section::AtmAndDebitCardSubtractions => "Total ATM and debit card subtractions",
+            Section::OtherSubtractions => "Total other subtractions",
+            Section::Checks => "Total checks",
+            Section::ServiceFees => "Total service fees",
+        }
+    }
+
+    fn transaction_type(self) -> TransactionType {
+        match self {
+            Section::DepositsAndOtherAdditions => TransactionType::Deposit,
+            Section::ServiceFees => TransactionType::Fee,
+            _ => TransactionType::Withdrawal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
     pub type_: TransactionType,
+    /// What the fee was for, for `TransactionType::Fee` transactions.
+    pub fee_kind: Option<FeeKind>,
+    pub section: Section,
+    /// The date the card was used, for descriptions such as "CHECKCARD 0410
+    /// ..." that give one; otherwise the posting date.
     pub date: Date,
+    /// The date printed in the date column, when the transaction posted.
+    pub posting_date: Date,
     pub description: String,
-    pub amount: i32,
+    /// The check number, for transactions from the "Checks" section.
+    pub check_number: Option<String>,
+    pub amount: Money,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BankOfAmericaDebitStatement {
     pub account_number: String,
     pub start_date: Date,
     pub end_date: Date,
-    pub start_balance: i32,
-    pub end_balance: i32,
+    pub start_balance: Money,
+    pub end_balance: Money,
     pub transactions: Vec<Transaction>,
+    /// The end-of-day balances from the "Daily ledger balances" table, in
+    /// date order. Empty if the statement has no such table.
+    pub daily_balances: Vec<(Date, Money)>,
+}
+
+impl From<&Transaction> for ReconciledTransaction {
+    fn from(transaction: &Transaction) -> Self {
+        ReconciledTransaction {
+            date: transaction.date,
+            description: transaction.description.clone(),
+            amount: transaction.amount,
+        }
+    }
 }
 
 fn dollar_amount_and_date_or_footer_follows(
     section_footer: &str,
-) -> impl Fn(&str) -> IResult<&str, i32> + '_ {
+) -> impl Fn(&str) -> PResult<'_, Money> + '_ {
     move |input| {
         let (input, amount) = preceded(multispace0, dollar_amount)(input)?;
         let (input, _) = peek(preceded(
@@ -54,57 +143,195 @@ fn dollar_amount_and_date_or_footer_follows(
     }
 }
 
-fn transaction(
-    section_footer: &str,
-    transaction_type: TransactionType,
-) -> impl Fn(&str) -> IResult<&str, Transaction> + '_ {
+/// How long a card transaction may take to post.
+const MAX_POSTING_DELAY_DAYS: i64 = 7;
+
+/// The card-swipe date that debit card descriptions embed as `MMDD`, as in
+/// "CHECKCARD 0410 COFFEE SHOP". Only a date in the week up to
+/// `posting_date` counts, so a street number as in "PURCHASE 1215 MAIN ST"
+/// isn't taken for one.
+fn card_date(description: &str, posting_date: Date) -> Option<Date> {
+    let window = StatementPeriod {
+        start_date: posting_date - Duration::days(MAX_POSTING_DELAY_DAYS),
+        end_date: posting_date,
+        tolerance: Duration::zero(),
+    };
+    ["CHECKCARD ", "PURCHASE "].iter().find_map(|prefix| {
+        let (_, (month, day)) = preceded(
+            take_until_including(prefix),
+            terminated(
+                pair(
+                    map_res(take(2usize), str::parse::<u32>),
+                    map_res(take(2usize), str::parse::<u32>),
+                ),
+                char(' '),
+            ),
+        )(description)
+        .ok()?;
+        infer_year(month, day, window)
+    })
+}
+
+fn transaction(section: Section) -> impl Fn(&str) -> PResult<'_, Transaction> {
     move |input| {
-        let (input, date) = month_day_year(input)?;
+        let transaction_type = section.transaction_type();
+        let (input, posting_date) = month_day_year(input)?;
         let (input, _) = multispace1(input)?;
         let (input, (description_chars, amount)) = many_till(
             anychar,
-            dollar_amount_and_date_or_footer_follows(section_footer),
+            dollar_amount_and_date_or_footer_follows(section.footer()),
         )(input)?;
         let (input, _) = multispace1(input)?;
+        let description: String = description_chars.into_iter().collect();
         Ok((
             input,
             Transaction {
                 type_: transaction_type,
-                date,
-                description: description_chars.into_iter().collect(),
+                fee_kind: (transaction_type == TransactionType::Fee)
+                    .then(|| FeeKind::classify(&description)),
+                section,
+                date: card_date(&description, posting_date).unwrap_or(posting_date),
+                posting_date,
+                description,
+                check_number: None,
                 amount,
             },
         ))
     }
 }
 
-fn transaction_section<'a>(
+/// A row of the "Checks" section, which has a check number instead of a
+/// description. Rows may be laid out several to a line.
+fn check_transaction(input: &str) -> PResult<'_, Transaction> {
+    let (input, date) = month_day_year(input)?;
+    let (input, check_number) = preceded(multispace1, digit1)(input)?;
+    // An asterisk marks a gap in the check number sequence.
+    let (input, _) = opt(char('*'))(input)?;
+    let (input, amount) = preceded(multispace1, dollar_amount)(input)?;
+    let (input, _) = multispace1(input)?;
+    Ok((
+        input,
+        Transaction {
+            type_: TransactionType::Withdrawal,
+            fee_kind: None,
+            section: Section::Checks,
+            date,
+            posting_date: date,
+            description: format!("Check {}", check_number),
+            check_number: Some(check_number.into()),
+            amount,
+        },
+    ))
+}
+
+fn section_start(section: Section) -> impl Fn(&str) -> PResult<'_, ()> {
+    move |input| {
+        let (input, ()) = take_until_including(section.header())(input)?;
+        let (input, _) = delimited(
+            multispace0,
+            alt((
+                tag("Date Description Amount"),
+                tag("Date Transaction description Amount"),
+                recognize(many1_count(terminated(
+                    tag("Date Check # Amount"),
+                    multispace0,
+                ))),
+            )),
+            multispace0,
+        )(input)?;
+        Ok((input, ()))
+    }
+}
+
+fn transaction_section(input: &str, section: Section) -> PResult<'_, Vec<Transaction>> {
+    context(section.header(), move |input| {
+        let (input, ()) = section_start(section)(input)?;
+        let (input, transactions) = if section == Section::Checks {
+            many0(check_transaction)(input)?
+        } else {
+            many0(transaction(section))(input)?
+        };
+        let (input, _) = tag(section.footer())(input)?;
+        let (input, total) = preceded(multispace1, dollar_amount)(input)?;
+        // Check the total
+        let computed_total = sum_amounts(input, transactions.iter().map(|t| t.amount))?;
+        verify_total(input, section.header(), total, computed_total, || {
+            transactions
+                .iter()
+                .map(ReconciledTransaction::from)
+                .collect()
+        })?;
+        Ok((input, transactions))
+    })(input)
+}
+
+/// Reads the "Daily ledger balances" table. Its entries run across several
+/// columns, so they are sorted by date before being returned.
+fn daily_ledger_balances(
+    period: StatementPeriod,
+) -> impl Fn(&str) -> PResult<'_, Vec<(Date, Money)>> {
+    move |input| {
+        let (input, ()) = take_until_including("Daily ledger balances")(input)?;
+        let (input, _) = many1_count(preceded(
+            multispace0,
+            alt((tag("Date"), tag("Balance ($)"), tag("Balance($)"))),
+        ))(input)?;
+        let (input, mut balances) = many0(preceded(
+            multispace0,
+            separated_pair(month_day_in(period), multispace1, dollar_amount),
+        ))(input)?;
+        balances.sort_by_key(|(date, _)| *date);
+        Ok((input, balances))
+    }
+}
+
+/// Checks each daily ledger balance against the start balance plus the
+/// transactions through that day, failing on the first day that drifts.
+fn verify_daily_balances<'a>(
     input: &'a str,
-    section_header: &str,
-    section_footer: &str,
-    transaction_type: TransactionType,
-) -> IResult<&'a str, Vec<Transaction>> {
-    let (input, ()) = take_until_including(section_header)(input)?;
-    let (input, _) = delimited(
-        multispace0,
-        alt((
-            tag("Date Description Amount"),
-            tag("Date Transaction description Amount"),
-        )),
-        multispace0,
-    )(input)?;
-    let (input, transactions) = many0(transaction(section_footer, transaction_type))(input)?;
-    let (input, _) = tag(section_footer)(input)?;
-    let (input, total) = preceded(multispace1, dollar_amount)(input)?;
-    // Check the total
-    let computed_total: i32 = transactions.iter().map(|t| t.amount).sum();
-    if computed_total != total {
-        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
-    }
-    Ok((input, transactions))
-}
-
-fn parse_statement(input: &str) -> IResult<&str, BankOfAmericaDebitStatement> {
+    start_balance: Money,
+    transactions: &[Transaction],
+    daily_balances: &[(Date, Money)],
+) -> Result<(), nom::Err<GrammarError<'a>>> {
+    let mut balance = start_balance;
+    let mut by_date: Vec<_> = transactions.iter().collect();
+    by_date.sort_by_key(|t| t.posting_date);
+    let mut remaining = by_date.as_slice();
+    for &(date, expected) in daily_balances {
+        let through_day = remaining.partition_point(|t| t.posting_date <= date);
+        let (applied, rest) = remaining.split_at(through_day);
+        remaining = rest;
+        balance = checked(
+            input,
+            balance.checked_add(sum_amounts(input, applied.iter().map(|t| t.amount))?),
+        )?;
+        verify_balance_on(
+            input,
+            "Daily ledger balances",
+            date,
+            expected,
+            balance,
+            || {
+                applied
+                    .iter()
+                    .filter(|t| t.posting_date == date)
+                    .map(|t| ReconciledTransaction::from(*t))
+                    .collect()
+            },
+        )?;
+    }
+    Ok(())
+}
+
+struct AccountSummary<'a> {
+    account_number: &'a str,
+    start_date: Date,
+    end_date: Date,
+    start_balance: Money,
+    end_balance: Money,
+}
+
+fn account_summary(input: &str) -> PResult<'_, AccountSummary<'_>> {
     let (input, ()) = take_until_including("Account number:")(input)?;
     let (input, account_number) = recognize(many1_count(preceded(multispace0, digit1)))(input)?;
 
@@ -115,40 +342,68 @@ fn parse_statement(input: &str) -> IResult<&str, BankOfAmericaDebitStatement> {
     let (input, ()) = take_until_including("Ending balance on ")(input)?;
     let (input, end_date) = month_word_day_year(input)?;
     let (input, end_balance) = preceded(multispace0, dollar_amount)(input)?;
-
-    let (input, mut transactions) = transaction_section(
+    Ok((
         input,
-        "Deposits and other additions",
-        "Total deposits and other additions",
-        TransactionType::Deposit,
-    )?;
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+        },
+    ))
+}
 
-    let (input, withdrawals) = transaction_section(
+fn parse_statement<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> PResult<'a, BankOfAmericaDebitStatement> {
+    let (
         input,
-        "Withdrawals and other subtractions",
-        "Total withdrawals and other subtractions",
-        TransactionType::Withdrawal,
-    )?;
-    transactions.extend(withdrawals.into_iter());
-
-    let (input, fees_present) = peek(opt(take_until("Service fees")))(input)?;
-    let (input, fees) = if fees_present.is_some() {
-        transaction_section(
-            input,
-            "Service fees",
-            "Total service fees",
-            TransactionType::Withdrawal,
-        )?
-    } else {
-        (input, Vec::new())
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+        },
+    ) = context("Account summary", account_summary)(input)?;
+    let period = StatementPeriod {
+        start_date,
+        end_date,
+        tolerance: options.date_tolerance,
     };
-    transactions.extend(fees.into_iter());
 
-    let computed_total: i32 = transactions.iter().map(|t| t.amount).sum();
-    if end_balance - start_balance != computed_total {
-        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+    let mut input = input;
+    let mut transactions = Vec::new();
+    for section in Section::ALL {
+        let (_, present) = peek(opt(section_start(section)))(input)?;
+        if present.is_some() {
+            let (rest, section_transactions) = transaction_section(input, section)?;
+            transactions.extend(section_transactions);
+            input = rest;
+        }
     }
 
+    let computed_total = sum_amounts(input, transactions.iter().map(|t| t.amount))?;
+    verify_total(
+        input,
+        "Account summary",
+        checked(input, end_balance.checked_sub(start_balance))?,
+        computed_total,
+        || {
+            transactions
+                .iter()
+                .map(ReconciledTransaction::from)
+                .collect()
+        },
+    )?;
+
+    let (input, daily_balances) =
+        context("Daily ledger balances", opt(daily_ledger_balances(period)))(input)?;
+    let daily_balances = daily_balances.unwrap_or_default();
+    verify_daily_balances(input, start_balance, &transactions, &daily_balances)?;
+
     Ok((
         input,
         BankOfAmericaDebitStatement {
@@ -158,14 +413,262 @@ fn parse_statement(input: &str) -> IResult<&str, BankOfAmericaDebitStatement> {
             start_balance,
             end_balance,
             transactions,
+            daily_balances,
         },
     ))
 }
 
+impl BankOfAmericaDebitStatement {
+    /// The balance after each transaction; see [`statement::running_balances`].
+    pub fn running_balances(&self) -> Option<Vec<RunningBalance<'_, Transaction>>> {
+        statement::running_balances(
+            self.start_balance,
+            &self.transactions,
+            |t| t.posting_date,
+            |t| t.amount,
+        )
+    }
+}
+
 impl StatementFormat for BankOfAmericaDebitStatement {
-    fn parse_file(path: &Path) -> Self {
-        let pdf_text = extract_text(&path).unwrap();
-        let (_, statement) = parse_statement(pdf_text.as_str()).unwrap();
-        statement
+    fn parse_text_with_options(text: &str, options: &ParseOptions) -> Result<Self, StatementError> {
+        finish(text, parse_statement(text, options))
+    }
+
+    fn preferred_extractor() -> Box<dyn TextExtractor> {
+        Format::BankOfAmericaDebit.preferred_extractor()
+    }
+}
+
+impl From<TransactionType> for TransactionKind {
+    fn from(transaction_type: TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Deposit => TransactionKind::Deposit,
+            TransactionType::Withdrawal => TransactionKind::Withdrawal,
+            TransactionType::Fee => TransactionKind::Fee,
+        }
+    }
+}
+
+impl From<Transaction> for statement::Transaction {
+    fn from(transaction: Transaction) -> Self {
+        statement::Transaction {
+            kind: transaction.type_.into(),
+            fee_kind: transaction.fee_kind,
+            date: transaction.date,
+            posting_date: Some(transaction.posting_date),
+            description: transaction.description,
+            reference_number: None,
+            check_number: transaction.check_number,
+            section: Some(transaction.section.header().into()),
+            card_number: None,
+            amount: transaction.amount,
+            printed_amount: transaction.amount,
+            foreign_exchange: None,
+            foreign_transaction_fee: None,
+        }
+    }
+}
+
+impl From<BankOfAmericaDebitStatement> for Statement {
+    fn from(statement: BankOfAmericaDebitStatement) -> Self {
+        Statement {
+            issuer: Issuer::BankOfAmerica,
+            account_kind: AccountKind::Checking,
+            account_number: statement.account_number,
+            start_date: statement.start_date,
+            end_date: statement.end_date,
+            start_balance: statement.start_balance,
+            end_balance: statement.end_balance,
+            total_interest: None,
+            credit_terms: None,
+            year_to_date: None,
+            rewards: None,
+            daily_balances: (!statement.daily_balances.is_empty())
+                .then_some(statement.daily_balances),
+            transactions: statement
+                .transactions
+                .into_iter()
+                .map(statement::Transaction::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = "\
+Bank of America
+Account number: 1234 5678 9012
+Beginning balance on April 1, 2023 $1,000.00
+Ending balance on April 30, 2023 $2,207.56
+
+Deposits and other additions
+Date Description Amount
+04/03/23 PAYROLL ACME CORP DES:PAYROLL 1,500.00
+Total deposits and other additions $1,500.00
+
+ATM and debit card subtractions
+Date Description Amount
+04/12/23 CHECKCARD 0410 COFFEE SHOP SEATTLE WA -5.44
+Total ATM and debit card subtractions -$5.44
+
+Other subtractions
+Date Description Amount
+04/15/23 ONLINE TRANSFER TO SAV -100.00
+Total other subtractions -$100.00
+
+Checks
+Date Check # Amount Date Check # Amount
+04/05/23 101 -50.00 04/09/23 103* -100.00
+04/20/23 104 -25.00
+Total checks -$175.00
+
+Service fees
+Date Transaction description Amount
+04/30/23 Monthly Maintenance Fee -12.00
+Total service fees -$12.00
+";
+
+    const LEDGER: &str = "
+Daily ledger balances
+Date Balance ($) Date Balance($) Date Balance ($)
+04/03 2,500.00 04/12 2,344.56 04/30 2,207.56
+04/05 2,450.00 04/15 2,244.56
+04/09 2,350.00 04/20 2,219.56
+";
+
+    fn date(month: u32, day: u32) -> Date {
+        Date::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_sub_sections_and_checks() {
+        let statement = BankOfAmericaDebitStatement::parse_text(STATEMENT).unwrap();
+        let sections: Vec<_> = statement.transactions.iter().map(|t| t.section).collect();
+        assert_eq!(
+            sections,
+            [
+                Section::DepositsAndOtherAdditions,
+                Section::AtmAndDebitCardSubtractions,
+                Section::OtherSubtractions,
+                Section::Checks,
+                Section::Checks,
+                Section::Checks,
+                Section::ServiceFees,
+            ]
+        );
+        let checks: Vec<_> = statement
+            .transactions
+            .iter()
+            .filter_map(|t| Some((t.check_number.as_deref()?, t.posting_date, t.amount)))
+            .collect();
+        assert_eq!(
+            checks,
+            [
+                ("101", date(4, 5), Money::usd(-5000)),
+                ("103", date(4, 9), Money::usd(-10000)),
+                ("104", date(4, 20), Money::usd(-2500)),
+            ]
+        );
+        let fee = statement.transactions.last().unwrap();
+        assert_eq!(fee.type_, TransactionType::Fee);
+        assert_eq!(fee.fee_kind, Some(FeeKind::MonthlyMaintenance));
+    }
+
+    #[test]
+    fn section_total_must_match() {
+        let text = STATEMENT.replace("Total checks -$175.00", "Total checks -$150.00");
+        let Err(StatementError::Reconciliation(error)) =
+            BankOfAmericaDebitStatement::parse_text(&text)
+        else {
+            panic!("expected a reconciliation error");
+        };
+        assert_eq!(error.section, "Checks");
+        assert_eq!(error.expected, Money::usd(-15000));
+        assert_eq!(error.computed, Money::usd(-17500));
+        assert_eq!(error.date, None);
+        assert_eq!(error.transactions.len(), 3);
+    }
+
+    #[test]
+    fn reads_daily_ledger_balances_in_date_order() {
+        let text = format!("{}{}", STATEMENT, LEDGER);
+        let statement = BankOfAmericaDebitStatement::parse_text(&text).unwrap();
+        let dates: Vec<_> = statement.daily_balances.iter().map(|(d, _)| *d).collect();
+        assert_eq!(
+            dates,
+            [
+                date(4, 3),
+                date(4, 5),
+                date(4, 9),
+                date(4, 12),
+                date(4, 15),
+                date(4, 20),
+                date(4, 30)
+            ]
+        );
+        assert_eq!(statement.daily_balances[3].1, Money::usd(234456));
+    }
+
+    #[test]
+    fn daily_ledger_balance_must_match() {
+        let text = format!("{}{}", STATEMENT, LEDGER.replace("2,344.56", "2,340.56"));
+        let Err(StatementError::Reconciliation(error)) =
+            BankOfAmericaDebitStatement::parse_text(&text)
+        else {
+            panic!("expected a reconciliation error");
+        };
+        assert_eq!(error.section, "Daily ledger balances");
+        assert_eq!(error.date, Some(date(4, 12)));
+        assert_eq!(error.expected, Money::usd(234056));
+        assert_eq!(error.computed, Money::usd(234456));
+        assert_eq!(
+            error.transactions,
+            [ReconciledTransaction {
+                date: date(4, 10),
+                description: "CHECKCARD 0410 COFFEE SHOP SEATTLE WA".into(),
+                amount: Money::usd(-544),
+            }]
+        );
+    }
+
+    #[test]
+    fn card_date_must_be_shortly_before_posting() {
+        assert_eq!(
+            card_date("CHECKCARD 0410 COFFEE SHOP", date(4, 12)),
+            Some(date(4, 10))
+        );
+        // A December swipe posted in January.
+        assert_eq!(
+            card_date(
+                "PURCHASE 1230 BOOKS",
+                Date::from_ymd_opt(2024, 1, 2).unwrap()
+            ),
+            Some(date(12, 30))
+        );
+        assert_eq!(
+            card_date("PURCHASE 1215 MAIN ST HARDWARE", date(3, 20)),
+            None
+        );
+        assert_eq!(card_date("CHECKCARD 0414 COFFEE SHOP", date(4, 12)), None);
+    }
+
+    #[test]
+    fn street_number_is_not_a_card_date() {
+        let text = STATEMENT.replace(
+            "04/15/23 ONLINE TRANSFER TO SAV",
+            "04/15/23 PURCHASE 1215 MAIN ST HARDWARE",
+        );
+        let statement = BankOfAmericaDebitStatement::parse_text(&text).unwrap();
+        let hardware = statement
+            .transactions
+            .iter()
+            .find(|t| t.description.contains("HARDWARE"))
+            .unwrap();
+        assert_eq!(hardware.date, date(4, 15));
+        assert_eq!(hardware.posting_date, date(4, 15));
     }
 }
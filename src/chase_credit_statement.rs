@@ -1,55 +1,131 @@
-use std::path::Path;
-
 use chrono::naive::NaiveDate as Date;
 use nom::{
     branch::alt,
-    bytes::complete::{tag},
+    bytes::complete::{tag, take_until},
     character::complete::{
-        anychar, digit1, multispace0, multispace1, newline, not_line_ending,
+        anychar, digit1, multispace0, multispace1, newline, not_line_ending, space0,
+    },
+    combinator::{
+        all_consuming, cond, consumed, map_opt, opt, peek, recognize, rest, value, verify,
     },
-    combinator::{cond, peek, recognize, value},
+    error::context,
     multi::{many0, many1_count, many_till},
     sequence::{delimited, preceded, separated_pair},
-    IResult,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::common_parsers::*;
-use crate::pdftotext::pdftotext;
-use crate::statement_format::StatementFormat;
+use crate::credit_terms::{apr_table, CreditTerms};
+use crate::detect::Format;
+use crate::error::{
+    finish, verify_total, GrammarError, PResult, ReconciledTransaction, StatementError,
+};
+use crate::extract::TextExtractor;
+use crate::foreign_exchange::{foreign_exchange, foreign_transaction_fees, ForeignExchange};
+use crate::money::Money;
+use crate::rewards::{rewards_summary, RewardsSummary};
+use crate::statement::{
+    self, AccountKind, FeeKind, Issuer, RunningBalance, Statement, TransactionKind,
+};
+use crate::statement_format::{ParseOptions, StatementFormat};
+use crate::year_to_date::{year_to_date, YearToDate};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TransactionType {
     Credit,
     Purchase,
+    CashAdvance,
+    BalanceTransfer,
     Fee,
+    Interest,
 }
 
-#[derive(Debug)]
+/// The ACCOUNT ACTIVITY sections, in the order Chase prints them. Any of them
+/// may be missing when there was no activity of that kind.
+const ACTIVITY_SECTIONS: [(&str, TransactionType); 6] = [
+    ("PAYMENTS AND OTHER CREDITS", TransactionType::Credit),
+    ("PURCHASE", TransactionType::Purchase),
+    ("CASH ADVANCE", TransactionType::CashAdvance),
+    ("BALANCE TRANSFER", TransactionType::BalanceTransfer),
+    ("FEES CHARGED", TransactionType::Fee),
+    ("INTEREST CHARGED", TransactionType::Interest),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
     pub type_: TransactionType,
+    /// What the fee was for, for `TransactionType::Fee` transactions.
+    pub fee_kind: Option<FeeKind>,
+    /// The transaction date.
     pub date: Date,
+    /// The posting date, on layouts that print one after the transaction date.
+    pub posting_date: Option<Date>,
     pub description: String,
-    pub amount: i32,
+    pub amount: Money,
+    /// The exchange details, for purchases made in another currency.
+    pub foreign_exchange: Option<ForeignExchange>,
+    /// Index in the statement's `transactions` of the foreign transaction fee
+    /// charged for this transaction.
+    pub foreign_transaction_fee: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ChaseCreditStatement {
     pub account_number: String,
     pub start_date: Date,
     pub end_date: Date,
-    pub start_balance: i32,
-    pub end_balance: i32,
+    pub start_balance: Money,
+    pub end_balance: Money,
     pub transactions: Vec<Transaction>,
-    pub total_interest: i32,
+    pub total_interest: Money,
+    pub credit_terms: CreditTerms,
+    pub year_to_date: Option<YearToDate>,
+    pub rewards: Option<RewardsSummary>,
+}
+
+impl From<&Transaction> for ReconciledTransaction {
+    fn from(transaction: &Transaction) -> Self {
+        ReconciledTransaction {
+            date: transaction.date,
+            description: transaction.description.clone(),
+            amount: transaction.amount,
+        }
+    }
+}
+
+/// The posting date column, when the layout has one: a zero-padded `MM/DD`
+/// on or after the transaction date. Descriptions such as "1/2 PRICE BOOKS"
+/// fail either test, so they aren't taken for a date.
+fn posting_date(period: StatementPeriod, date: Date) -> impl Fn(&str) -> PResult<'_, Date> {
+    move |input| {
+        let (input, posting_date) = preceded(
+            multispace1,
+            verify(
+                map_opt(
+                    verify(consumed(month_day), |(printed, _): &(&str, _)| {
+                        printed.len() == "MM/DD".len()
+                    }),
+                    |(_, (month, day))| infer_year(month, day, period),
+                ),
+                |posting_date| *posting_date >= date,
+            ),
+        )(input)?;
+        let (input, _) = peek(multispace1)(input)?;
+        Ok((input, posting_date))
+    }
 }
 
 fn transaction(
-    start_date: &Date,
+    period: StatementPeriod,
     transaction_type: TransactionType,
-) -> impl Fn(&str) -> IResult<&str, Transaction> + '_ {
+) -> impl Fn(&str) -> PResult<'_, Transaction> {
     move |input| {
-        let (input, (month, day)) = preceded(tag("  "), month_day)(input)?;
-        let date = infer_year(month, day, *start_date).unwrap();
+        let (input, date) = preceded(tag("  "), month_day_in(period))(input)?;
+        let (input, posting_date) = opt(posting_date(period, date))(input)?;
         let (input, _) = multispace1(input)?;
         let (input, (description_chars, amount)) =
             many_till(anychar, delimited(multispace0, dollar_amount, newline))(input)?;
@@ -58,21 +134,33 @@ fn transaction(
             peek(alt((
                 value((), preceded(tag("  "), month_day)),
                 value((), newline),
+                value((), preceded(space0, tag("TOTAL "))),
             ))),
         )(input)?;
-        let (input, _) = cond(additional_desc.len() > 0, newline)(input)?;
+        let (input, _) = cond(!additional_desc.is_empty(), newline)(input)?;
         let mut description: String = description_chars.into_iter().collect();
-        additional_desc.into_iter().for_each(|s| {
-            description += "\n";
-            description += s
-        });
+        let mut exchange = None;
+        for line in additional_desc {
+            match all_consuming(foreign_exchange)(line.trim()) {
+                Ok((_, parsed)) => exchange = Some(parsed),
+                Err(_) => {
+                    description += "\n";
+                    description += line
+                }
+            }
+        }
         Ok((
             input,
             Transaction {
                 type_: transaction_type,
+                fee_kind: (transaction_type == TransactionType::Fee)
+                    .then(|| FeeKind::classify(&description)),
                 date,
+                posting_date,
                 description,
                 amount,
+                foreign_exchange: exchange,
+                foreign_transaction_fee: None,
             },
         ))
     }
@@ -80,39 +168,211 @@ fn transaction(
 
 fn transaction_section<'a>(
     input: &'a str,
-    start_date: &Date,
-    section_header: &str,
+    period: StatementPeriod,
+    section_header: &'static str,
     transaction_type: TransactionType,
-) -> IResult<&'a str, Vec<Transaction>> {
-    let (input, ()) = take_until_including(section_header)(input)?;
-    let (input, _) = tag("\n\n")(input)?;
-    let (input, transactions) = many0(transaction(start_date, transaction_type))(input)?;
-    Ok((input, transactions))
+) -> PResult<'a, Vec<Transaction>> {
+    context(section_header, move |input| {
+        let (input, ()) = take_until_line(section_header)(input)?;
+        let (input, _) = tag("\n\n")(input)?;
+        let (input, transactions) = many0(transaction(period, transaction_type))(input)?;
+        Ok((input, transactions))
+    })(input)
+}
+
+/// Reads "TOTAL INTEREST FOR THIS PERIOD" from the interest charged activity,
+/// checking it against the "Interest Charged" line of the account summary.
+/// Statements without interest may omit either, in which case the other (or
+/// zero) is used.
+fn total_interest(
+    input: &str,
+    end_date: Date,
+    interest_charged: Option<Money>,
+) -> PResult<'_, Money> {
+    let (input, period_total) = opt(preceded(
+        take_until_including("TOTAL INTEREST FOR THIS PERIOD"),
+        preceded(multispace0, dollar_amount),
+    ))(input)?;
+    match (period_total, interest_charged) {
+        (Some(period_total), Some(interest_charged)) => {
+            verify_total(
+                input,
+                "INTEREST CHARGED",
+                interest_charged,
+                period_total,
+                || {
+                    vec![ReconciledTransaction {
+                        date: end_date,
+                        description: "TOTAL INTEREST FOR THIS PERIOD".into(),
+                        amount: period_total,
+                    }]
+                },
+            )?;
+            Ok((input, period_total))
+        }
+        (Some(total), None) | (None, Some(total)) => Ok((input, total)),
+        (None, None) => Ok((input, Money::usd(0))),
+    }
+}
+
+fn credit_terms(input: &str) -> Result<CreditTerms, nom::Err<GrammarError<'_>>> {
+    Ok(CreditTerms {
+        payment_due_date: labeled(input, "Payment Due Date", month_day_year)?,
+        minimum_payment: labeled(input, "Minimum Payment Due", dollar_amount)?,
+        credit_line: labeled(input, "Credit Access Line", whole_dollar_amount)?,
+        available_credit: labeled(input, "Available Credit", whole_dollar_amount)?,
+        cash_access_line: labeled(input, "Cash Access Line", whole_dollar_amount)?,
+        late_fee: labeled(input, "late fee of up to", whole_dollar_amount)?,
+        aprs: apr_table(input, "INTEREST CHARGES")?,
+    })
 }
 
-fn parse_statement(input: &str) -> IResult<&str, ChaseCreditStatement> {
+struct AccountSummary<'a> {
+    account_number: &'a str,
+    start_date: Date,
+    end_date: Date,
+    start_balance: Money,
+    end_balance: Money,
+    interest_charged: Option<Money>,
+}
+
+fn account_summary(input: &str) -> PResult<'_, AccountSummary<'_>> {
     let (input, ()) = take_until_including("ACCOUNT SUMMARY")(input)?;
     let (input, ()) = take_until_including("Account Number: ")(input)?;
     let (input, account_number) = recognize(many1_count(preceded(multispace0, digit1)))(input)?;
     let (input, _) = take_until_including("Previous Balance")(input)?;
     let (input, start_balance) = preceded(multispace0, dollar_amount)(input)?;
+    let (input, summary_lines) = take_until("New Balance")(input)?;
+    let (_, interest_charged) = opt(preceded(
+        take_until_including("Interest Charged"),
+        preceded(multispace0, dollar_amount),
+    ))(summary_lines)?;
     let (input, ()) = take_until_including("New Balance")(input)?;
     let (input, end_balance) = preceded(multispace0, dollar_amount)(input)?;
     let (input, _) = delimited(multispace0, tag("Opening/Closing Date"), multispace0)(input)?;
     let (input, (start_date, end_date)) =
         separated_pair(month_day_year, tag(" - "), month_day_year)(input)?;
+    Ok((
+        input,
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+            interest_charged,
+        },
+    ))
+}
+
+fn parse_statement<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> PResult<'a, ChaseCreditStatement> {
+    let credit_terms = credit_terms(input)?;
+    let year_to_date = year_to_date(input)?;
+    let rewards = rewards_summary(input, &["ULTIMATE REWARDS"]);
+    let (
+        input,
+        AccountSummary {
+            account_number,
+            start_date,
+            end_date,
+            start_balance,
+            end_balance,
+            interest_charged,
+        },
+    ) = context("ACCOUNT SUMMARY", account_summary)(input)?;
+    let period = StatementPeriod {
+        start_date,
+        end_date,
+        tolerance: options.date_tolerance,
+    };
     let (input, ()) = take_until_including("ACCOUNT ACTIVITY")(input)?;
+    // The activity ends where the INTEREST CHARGES rate table starts.
+    let (input, activity) = alt((take_until("INTEREST CHARGES"), rest))(input)?;
+
+    let mut transactions = Vec::new();
+    let mut remaining = activity;
+    for (section_header, transaction_type) in ACTIVITY_SECTIONS {
+        let (_, present) = peek(opt(take_until_line(section_header)))(remaining)?;
+        if present.is_some() {
+            let (rest, section) =
+                transaction_section(remaining, period, section_header, transaction_type)?;
+            transactions.extend(section);
+            remaining = rest;
+        }
+    }
 
-    let (input, mut transactions) = transaction_section(
+    let fees = foreign_transaction_fees(
+        &transactions,
+        |t| t.date,
+        |t| t.foreign_exchange.is_some(),
+        |t| t.fee_kind == Some(FeeKind::ForeignTransaction),
+    );
+    for (transaction, fee) in transactions.iter_mut().zip(fees) {
+        transaction.foreign_transaction_fee = fee;
+    }
+
+    let (_, total_fees) = opt(preceded(
+        take_until_including("TOTAL FEES FOR THIS PERIOD"),
+        preceded(multispace0, dollar_amount),
+    ))(activity)?;
+    if let Some(total_fees) = total_fees {
+        let fees: Vec<_> = transactions
+            .iter()
+            .filter(|t| t.type_ == TransactionType::Fee)
+            .collect();
+        let computed_fees = sum_amounts(remaining, fees.iter().map(|t| t.amount))?;
+        verify_total(remaining, "FEES CHARGED", total_fees, computed_fees, || {
+            fees.into_iter().map(ReconciledTransaction::from).collect()
+        })?;
+    }
+
+    let (_, total_interest) = context("INTEREST CHARGED", |input| {
+        total_interest(input, end_date, interest_charged)
+    })(activity)?;
+    let (interest, other): (Vec<_>, Vec<_>) = transactions
+        .iter()
+        .partition(|t| t.type_ == TransactionType::Interest);
+    if !interest.is_empty() {
+        let computed_interest = sum_amounts(remaining, interest.iter().map(|t| t.amount))?;
+        verify_total(
+            remaining,
+            "INTEREST CHARGED",
+            total_interest,
+            computed_interest,
+            || {
+                interest
+                    .into_iter()
+                    .map(ReconciledTransaction::from)
+                    .collect()
+            },
+        )?;
+    }
+
+    // Interest is counted once, from the period total, whether or not it was
+    // also itemized.
+    let computed_total = sum_amounts(
         input,
-        &start_date,
-        "PAYMENTS AND OTHER CREDITS",
-        TransactionType::Credit,
+        other.iter().map(|t| t.amount).chain([total_interest]),
+    )?;
+    verify_total(
+        input,
+        "ACCOUNT SUMMARY",
+        checked(input, end_balance.checked_sub(start_balance))?,
+        computed_total,
+        || {
+            let mut reconciled: Vec<_> =
+                other.into_iter().map(ReconciledTransaction::from).collect();
+            reconciled.push(ReconciledTransaction {
+                date: end_date,
+                description: "TOTAL INTEREST FOR THIS PERIOD".into(),
+                amount: total_interest,
+            });
+            reconciled
+        },
     )?;
-
-    let (input, purchases) =
-        transaction_section(input, &start_date, "PURCHASE", TransactionType::Purchase)?;
-    transactions.extend(purchases.into_iter());
 
     Ok((
         input,
@@ -123,17 +383,233 @@ fn parse_statement(input: &str) -> IResult<&str, ChaseCreditStatement> {
             start_balance,
             end_balance,
             transactions,
-            // TODO: Find total interest
-            total_interest: 0,
+            total_interest,
+            credit_terms,
+            year_to_date,
+            rewards,
         },
     ))
 }
 
+impl ChaseCreditStatement {
+    /// The balance after each transaction; see [`statement::running_balances`].
+    pub fn running_balances(&self) -> Option<Vec<RunningBalance<'_, Transaction>>> {
+        statement::running_balances(
+            self.start_balance,
+            &self.transactions,
+            |t| t.posting_date.unwrap_or(t.date),
+            |t| t.amount,
+        )
+    }
+}
+
 impl StatementFormat for ChaseCreditStatement {
-    fn parse_file(path: &Path) -> Self {
-        let pdf_text = pdftotext(&path, true).unwrap();
-        println!("{}", pdf_text);
-        let (_, statement) = parse_statement(pdf_text.as_str()).unwrap();
-        statement
+    fn parse_text_with_options(text: &str, options: &ParseOptions) -> Result<Self, StatementError> {
+        finish(text, parse_statement(text, options))
+    }
+
+    fn preferred_extractor() -> Box<dyn TextExtractor> {
+        Format::ChaseCredit.preferred_extractor()
+    }
+}
+
+impl From<TransactionType> for TransactionKind {
+    fn from(transaction_type: TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Credit => TransactionKind::Credit,
+            TransactionType::Purchase => TransactionKind::Purchase,
+            TransactionType::CashAdvance => TransactionKind::CashAdvance,
+            TransactionType::BalanceTransfer => TransactionKind::BalanceTransfer,
+            TransactionType::Fee => TransactionKind::Fee,
+            TransactionType::Interest => TransactionKind::Interest,
+        }
+    }
+}
+
+impl From<Transaction> for statement::Transaction {
+    fn from(transaction: Transaction) -> Self {
+        statement::Transaction {
+            kind: transaction.type_.into(),
+            fee_kind: transaction.fee_kind,
+            date: transaction.date,
+            posting_date: transaction.posting_date,
+            description: transaction.description,
+            reference_number: None,
+            check_number: None,
+            section: None,
+            card_number: None,
+            amount: AccountKind::Credit.normalize(transaction.amount),
+            printed_amount: transaction.amount,
+            foreign_exchange: transaction.foreign_exchange,
+            foreign_transaction_fee: transaction.foreign_transaction_fee,
+        }
+    }
+}
+
+impl From<ChaseCreditStatement> for Statement {
+    fn from(statement: ChaseCreditStatement) -> Self {
+        Statement {
+            issuer: Issuer::Chase,
+            account_kind: AccountKind::Credit,
+            account_number: statement.account_number,
+            start_date: statement.start_date,
+            end_date: statement.end_date,
+            start_balance: statement.start_balance,
+            end_balance: statement.end_balance,
+            total_interest: Some(statement.total_interest),
+            credit_terms: Some(statement.credit_terms),
+            year_to_date: statement.year_to_date,
+            rewards: statement.rewards,
+            daily_balances: None,
+            transactions: statement
+                .transactions
+                .into_iter()
+                .map(statement::Transaction::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ReconciliationError;
+
+    const STATEMENT: &str = "                    ACCOUNT SUMMARY
+Account Number: 4266 8412 3456 7890
+Previous Balance                     $1,000.00
+Payment, Credits                     -$1,000.00
+Purchases                              +$62.34
+Cash Advances                           $20.00
+Fees Charged                            $10.34
+Interest Charged                         +$3.21
+New Balance                             $95.89
+Opening/Closing Date          12/15/22 - 01/14/23
+
+ACCOUNT ACTIVITY
+
+  Date of
+Transaction        Merchant Name or Transaction Description          $ Amount
+PAYMENTS AND OTHER CREDITS
+
+  01/05     AUTOMATIC PAYMENT - THANK YOU                            -1,000.00
+
+PURCHASE
+
+  12/20     AMAZON MKTPLACE                                             50.00
+  01/02     CAFE PARIS                                                  12.34
+
+CASH ADVANCE
+
+  01/03     ATM WITHDRAWAL                                              20.00
+
+FEES CHARGED
+
+  01/03     CASH ADVANCE FEE                                            10.00
+  01/02     FOREIGN TRANSACTION FEE                                      0.34
+  TOTAL FEES FOR THIS PERIOD                                          $10.34
+
+INTEREST CHARGED
+
+  01/14     INTEREST CHARGE ON PURCHASES                                 3.21
+  TOTAL INTEREST FOR THIS PERIOD                                        $3.21
+
+                    INTEREST CHARGES
+";
+
+    fn reconciliation_error(text: &str) -> ReconciliationError {
+        match ChaseCreditStatement::parse_text(text) {
+            Err(StatementError::Reconciliation(error)) => error,
+            result => panic!("expected a reconciliation error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parses_optional_activity_sections() {
+        let statement = ChaseCreditStatement::parse_text(STATEMENT).unwrap();
+        let types: Vec<_> = statement.transactions.iter().map(|t| t.type_).collect();
+        assert_eq!(
+            types,
+            [
+                TransactionType::Credit,
+                TransactionType::Purchase,
+                TransactionType::Purchase,
+                TransactionType::CashAdvance,
+                TransactionType::Fee,
+                TransactionType::Fee,
+                TransactionType::Interest,
+            ]
+        );
+        // The section totals aren't read as continuation lines.
+        assert_eq!(
+            statement.transactions[5].description,
+            "FOREIGN TRANSACTION FEE"
+        );
+        assert_eq!(
+            statement.transactions[6].description,
+            "INTEREST CHARGE ON PURCHASES"
+        );
+        assert_eq!(statement.total_interest, Money::usd(321));
+    }
+
+    #[test]
+    fn fee_total_must_match() {
+        let error = reconciliation_error(&STATEMENT.replace("$10.34\n\n", "$10.00\n\n"));
+        assert_eq!(error.section, "FEES CHARGED");
+        assert_eq!(error.expected, Money::usd(1000));
+        assert_eq!(error.computed, Money::usd(1034));
+        assert_eq!(error.transactions.len(), 2);
+    }
+
+    #[test]
+    fn interest_total_must_match_summary() {
+        let error = reconciliation_error(&STATEMENT.replace(
+            "TOTAL INTEREST FOR THIS PERIOD                                        $3.21",
+            "TOTAL INTEREST FOR THIS PERIOD                                        $3.50",
+        ));
+        assert_eq!(error.section, "INTEREST CHARGED");
+        assert_eq!(error.expected, Money::usd(321));
+        assert_eq!(error.computed, Money::usd(350));
+    }
+
+    #[test]
+    fn itemized_interest_must_match_total() {
+        let error = reconciliation_error(&STATEMENT.replace(
+            "INTEREST CHARGE ON PURCHASES                                 3.21",
+            "INTEREST CHARGE ON PURCHASES                                 3.00",
+        ));
+        assert_eq!(error.section, "INTEREST CHARGED");
+        assert_eq!(error.expected, Money::usd(321));
+        assert_eq!(error.computed, Money::usd(300));
+        assert_eq!(
+            error.transactions[0].description,
+            "INTEREST CHARGE ON PURCHASES"
+        );
+    }
+
+    #[test]
+    fn interest_from_summary_alone_reconciles() {
+        let start = STATEMENT.find("INTEREST CHARGED\n\n").unwrap();
+        let end = STATEMENT
+            .find("                    INTEREST CHARGES")
+            .unwrap();
+        let text = format!("{}{}", &STATEMENT[..start], &STATEMENT[end..]);
+        let statement = ChaseCreditStatement::parse_text(&text).unwrap();
+        assert_eq!(statement.total_interest, Money::usd(321));
+        assert!(statement
+            .transactions
+            .iter()
+            .all(|t| t.type_ != TransactionType::Interest));
+    }
+
+    #[test]
+    fn account_summary_must_balance() {
+        let error = reconciliation_error(&STATEMENT.replace(
+            "AMAZON MKTPLACE                                             50.00",
+            "AMAZON MKTPLACE                                             55.00",
+        ));
+        assert_eq!(error.section, "ACCOUNT SUMMARY");
+        assert_eq!(error.expected, Money::usd(-90411));
+        assert_eq!(error.computed, Money::usd(-89911));
     }
 }
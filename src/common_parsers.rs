@@ -1,74 +1,326 @@
-use chrono::{naive::NaiveDate as Date, Datelike, Month};
+use chrono::{naive::NaiveDate as Date, Datelike, Duration, Month};
 use nom::{
     bytes::complete::{tag, take_until},
     character::complete::{alpha1, char, digit1, i32, multispace0, multispace1, u32},
-    combinator::{map_opt, map_res, opt},
+    combinator::{map_opt, map_res, opt, verify},
+    error::{ErrorKind, ParseError},
     multi::separated_list0,
-    sequence::{delimited, preceded, separated_pair, tuple},
-    IResult,
+    sequence::{delimited, pair, preceded, separated_pair},
 };
 
-pub fn month_word(input: &str) -> IResult<&str, Month> {
+use crate::error::{GrammarError, PResult};
+use crate::money::{Currency, Money, Rate};
+
+pub fn month_word(input: &str) -> PResult<'_, Month> {
     map_res(alpha1, |x: &str| x.parse::<Month>())(input)
 }
 
-pub fn month_word_day(input: &str) -> IResult<&str, (Month, u32)> {
-    separated_pair(month_word, multispace1, u32)(input)
+pub fn month_word_day(input: &str) -> PResult<'_, (Month, u32)> {
+    verify(separated_pair(month_word, multispace1, u32), |&(_, day)| {
+        (1..=31).contains(&day)
+    })(input)
 }
 
-pub fn month_day(input: &str) -> IResult<&str, (u32, u32)> {
-    separated_pair(u32, char('/'), u32)(input)
+/// A month and day such as `04/15`, rejecting numbers that can't be either.
+pub fn month_day(input: &str) -> PResult<'_, (u32, u32)> {
+    verify(separated_pair(u32, char('/'), u32), |&(month, day)| {
+        (1..=12).contains(&month) && (1..=31).contains(&day)
+    })(input)
 }
 
-pub fn month_day_year(input: &str) -> IResult<&str, Date> {
-    let (input, (month, _, day, _, year)) = tuple((u32, char('/'), u32, char('/'), i32))(input)?;
-    Ok((input, Date::from_ymd_opt(2000 + year, month, day).unwrap()))
+/// A two-digit year in this century, such as Chase prints, or a four-digit
+/// one, such as Bank of America prints.
+pub fn year(input: &str) -> PResult<'_, i32> {
+    let (input, digits) = verify(digit1, |digits: &str| {
+        digits.len() == 2 || digits.len() == 4
+    })(input)?;
+    let (_, year) = i32(digits)?;
+    Ok((input, if digits.len() == 2 { 2000 + year } else { year }))
 }
 
-pub fn infer_year(month: u32, day: u32, start_date: Date) -> Option<Date> {
-    let year = if month < start_date.month() {
-        start_date.year() + 1
-    } else {
-        start_date.year()
-    };
-    Date::from_ymd_opt(year, month, day)
+/// A date such as `04/15/23` or `04/15/2023`. Impossible dates are an error,
+/// so alternatives can still be tried.
+pub fn month_day_year(input: &str) -> PResult<'_, Date> {
+    map_opt(
+        separated_pair(month_day, char('/'), year),
+        |((month, day), year)| Date::from_ymd_opt(year, month, day),
+    )(input)
+}
+
+/// The dates a statement covers, used to date transactions printed without a
+/// year.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StatementPeriod {
+    pub start_date: Date,
+    pub end_date: Date,
+    /// How far outside the period a transaction may be dated, e.g. a credit
+    /// for a purchase made before the period started.
+    pub tolerance: Duration,
+}
+
+impl StatementPeriod {
+    /// How far `date` falls outside the period, or zero if within it.
+    fn distance(&self, date: Date) -> Duration {
+        if date < self.start_date {
+            self.start_date - date
+        } else if date > self.end_date {
+            date - self.end_date
+        } else {
+            Duration::zero()
+        }
+    }
+}
+
+/// Dates a month and day printed without a year: of the years around
+/// `period`, picks the one that puts the date closest to it. `None` if the
+/// date is more than `period.tolerance` outside the period in every year.
+pub fn infer_year(month: u32, day: u32, period: StatementPeriod) -> Option<Date> {
+    (period.start_date.year() - 1..=period.end_date.year() + 1)
+        .filter_map(|year| Date::from_ymd_opt(year, month, day))
+        .min_by_key(|date| period.distance(*date))
+        .filter(|date| period.distance(*date) <= period.tolerance)
+}
+
+/// A month and day dated with `infer_year`. Once the month and day have
+/// parsed, a date that can't be placed near `period` fails the whole parse,
+/// as it means the statement was misread.
+pub fn month_day_in(period: StatementPeriod) -> impl Fn(&str) -> PResult<'_, Date> {
+    move |input| {
+        let (rest, (month, day)) = month_day(input)?;
+        match infer_year(month, day, period) {
+            Some(date) => Ok((rest, date)),
+            None => Err(nom::Err::Failure(GrammarError::from_error_kind(
+                input,
+                ErrorKind::Verify,
+            ))),
+        }
+    }
 }
 
-pub fn month_word_day_year(input: &str) -> IResult<&str, Date> {
+pub fn month_word_day_year(input: &str) -> PResult<'_, Date> {
     let (input, (month, day)) = month_word_day(input)?;
     let (input, _) = delimited(multispace0, opt(char(',')), multispace0)(input)?;
-    map_opt(i32, move |year| {
+    map_opt(year, move |year| {
         Date::from_ymd_opt(year, month.number_from_month(), day)
     })(input)
 }
 
-pub fn dollar_amount(input: &str) -> IResult<&str, i32> {
+fn amount(input: &str, cents_required: bool) -> PResult<'_, Money> {
+    let start = input;
     let (input, negate) = opt(char('-'))(input)?;
     let (input, _) = opt(char('+'))(input)?;
     let (input, _) = opt(char('$'))(input)?;
     let (input, dollars_strs) = separated_list0(char(','), digit1)(input)?;
-    let (input, cents_str) = preceded(char('.'), digit1)(input)?;
-    let cents = cents_str.parse::<i32>().unwrap();
-    let dollars = if dollars_strs.len() > 0 {
-        (dollars_strs.into_iter().collect::<String>())
-            .parse::<i32>()
-            .unwrap()
+    let mut cents = preceded(char('.'), verify(digit1, |s: &str| s.len() == 2));
+    let (input, cents_str) = if cents_required {
+        cents(input)?
+    } else if dollars_strs.is_empty() {
+        return Err(nom::Err::Error(GrammarError::from_error_kind(
+            input,
+            ErrorKind::Digit,
+        )));
     } else {
-        0
+        let (input, cents_str) = opt(cents)(input)?;
+        (input, cents_str.unwrap_or("00"))
     };
-    let abs_amount = dollars * 100 + cents;
-    let amount = if negate.is_some() {
-        -abs_amount
+    let abs_cents = dollars_strs
+        .into_iter()
+        .chain([cents_str])
+        .flat_map(str::chars)
+        .try_fold(0i64, |total, digit| {
+            total
+                .checked_mul(10)?
+                .checked_add(digit.to_digit(10)?.into())
+        });
+    let cents = if negate.is_some() {
+        abs_cents.map(|cents| -cents)
     } else {
-        abs_amount
+        abs_cents
     };
-    Ok((input, amount))
+    match cents {
+        Some(cents) => Ok((input, Money::usd(cents))),
+        None => Err(overflow(start)),
+    }
+}
+
+pub fn dollar_amount(input: &str) -> PResult<'_, Money> {
+    amount(input, true)
+}
+
+/// Like `dollar_amount`, but the cents may be left off, as in `$5,000`.
+pub fn whole_dollar_amount(input: &str) -> PResult<'_, Money> {
+    amount(input, false)
+}
+
+/// A decimal number such as `19.99` or `1.1218`, kept exactly as printed.
+pub fn rate(input: &str) -> PResult<'_, Rate> {
+    let start = input;
+    let (input, whole) = digit1(input)?;
+    let (input, fraction) = opt(preceded(char('.'), digit1))(input)?;
+    let fraction = fraction.unwrap_or("");
+    let units = whole
+        .chars()
+        .chain(fraction.chars())
+        .try_fold(0i64, |total, digit| {
+            total
+                .checked_mul(10)?
+                .checked_add(digit.to_digit(10)?.into())
+        });
+    match units {
+        Some(units) => Ok((input, Rate::new(units, fraction.len() as u32))),
+        None => Err(overflow(start)),
+    }
+}
+
+fn overflow(input: &str) -> nom::Err<GrammarError<'_>> {
+    nom::Err::Failure(GrammarError::from_error_kind(input, ErrorKind::TooLarge))
+}
+
+/// Unwraps the result of checked `Money` arithmetic, failing the parse at
+/// `input` if it overflowed.
+pub fn checked(input: &str, amount: Option<Money>) -> Result<Money, nom::Err<GrammarError<'_>>> {
+    amount.ok_or_else(|| overflow(input))
 }
 
-pub fn take_until_including(t: &str) -> impl Fn(&str) -> IResult<&str, ()> + '_ {
+/// Adds up dollar amounts, failing the parse at `input` if they overflow.
+pub fn sum_amounts(
+    input: &str,
+    amounts: impl IntoIterator<Item = Money>,
+) -> Result<Money, nom::Err<GrammarError<'_>>> {
+    checked(input, Money::checked_sum(Currency::USD, amounts))
+}
+
+pub fn take_until_including(t: &str) -> impl Fn(&str) -> PResult<'_, ()> + '_ {
     move |input| {
         let (input, _) = take_until(t)(input)?;
         let (input, _) = tag(t)(input)?;
         Ok((input, ()))
     }
 }
+
+/// Finds the first `label` in `input` and parses `value` after it and an
+/// optional colon. `None` if the label is missing or isn't followed by a
+/// value.
+pub fn labeled<'a, O>(
+    input: &'a str,
+    label: &str,
+    value: impl FnMut(&'a str) -> PResult<'a, O>,
+) -> Result<Option<O>, nom::Err<GrammarError<'a>>> {
+    let (_, found) = opt(preceded(
+        take_until_including(label),
+        preceded(pair(opt(char(':')), multispace0), value),
+    ))(input)?;
+    Ok(found)
+}
+
+/// Skips whole lines until one that reads just `line` once surrounding
+/// whitespace is trimmed, and consumes it up to (not including) its newline.
+/// Unlike `take_until_including`, this won't stop inside a longer line that
+/// merely contains `line`, such as a description mentioning a section name.
+pub fn take_until_line(line: &str) -> impl Fn(&str) -> PResult<'_, ()> + '_ {
+    move |input| {
+        let mut rest = input;
+        loop {
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            if rest[..line_end].trim() == line {
+                return Ok((&rest[line_end..], ()));
+            }
+            if line_end == rest.len() {
+                return Err(nom::Err::Error(GrammarError::from_error_kind(
+                    input,
+                    ErrorKind::TakeUntil,
+                )));
+            }
+            rest = &rest[line_end + 1..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn period(start_date: Date, end_date: Date) -> StatementPeriod {
+        StatementPeriod {
+            start_date,
+            end_date,
+            tolerance: Duration::days(90),
+        }
+    }
+
+    #[test]
+    fn parses_dollar_amounts() {
+        assert_eq!(
+            dollar_amount("$1,234.56 x").ok(),
+            Some((" x", Money::usd(123456)))
+        );
+        assert_eq!(dollar_amount("-0.05").ok(), Some(("", Money::usd(-5))));
+        assert!(dollar_amount("$5,000").is_err());
+        assert_eq!(
+            whole_dollar_amount("$5,000").ok(),
+            Some(("", Money::usd(500000)))
+        );
+    }
+
+    #[test]
+    fn overflowing_amount_fails() {
+        let Err(nom::Err::Failure(error)) = dollar_amount("92,233,720,368,547,758.08") else {
+            panic!("expected a failure");
+        };
+        assert_eq!(error.kind, ErrorKind::TooLarge);
+        assert_eq!(
+            dollar_amount("-92,233,720,368,547,758.07").ok(),
+            Some(("", Money::usd(-i64::MAX)))
+        );
+    }
+
+    #[test]
+    fn parses_two_and_four_digit_years() {
+        assert_eq!(
+            month_day_year("04/15/23").ok(),
+            Some(("", date(2023, 4, 15)))
+        );
+        assert_eq!(
+            month_day_year("04/15/2023").ok(),
+            Some(("", date(2023, 4, 15)))
+        );
+        assert!(year("123").is_err());
+    }
+
+    #[test]
+    fn rejects_impossible_dates() {
+        assert!(month_day_year("13/45/23").is_err());
+        assert!(month_day_year("02/30/2023").is_err());
+        assert!(month_day("0/12").is_err());
+    }
+
+    #[test]
+    fn parses_month_word_dates() {
+        assert_eq!(
+            month_word_day_year("March 5, 2023").ok(),
+            Some(("", date(2023, 3, 5)))
+        );
+        assert!(month_word_day_year("March 5, 99999999999").is_err());
+    }
+
+    #[test]
+    fn infers_year_across_new_year() {
+        let january = period(date(2023, 12, 20), date(2024, 1, 19));
+        assert_eq!(infer_year(12, 28, january), Some(date(2023, 12, 28)));
+        assert_eq!(infer_year(1, 5, january), Some(date(2024, 1, 5)));
+        // A December credit for a purchase made before the period started.
+        let statement = period(date(2024, 1, 3), date(2024, 2, 2));
+        assert_eq!(infer_year(12, 15, statement), Some(date(2023, 12, 15)));
+    }
+
+    #[test]
+    fn infer_year_respects_tolerance() {
+        let statement = period(date(2024, 1, 3), date(2024, 2, 2));
+        assert_eq!(infer_year(7, 1, statement), None);
+        assert_eq!(infer_year(2, 30, statement), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Payment terms and interest rates printed on credit card statements. The
+//! figures are scattered over the account summary, payment information and
+//! interest charge sections, so each one is looked up by its label rather
+//! than parsed in order. A figure the statement doesn't print is `None`.
+
+use chrono::naive::NaiveDate as Date;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{alpha1, anychar, char, multispace1, one_of, space1},
+    combinator::{opt, peek, recognize, value},
+    multi::{many0, many_till},
+    sequence::{delimited, preceded, terminated},
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::common_parsers::*;
+use crate::error::{GrammarError, PResult};
+use crate::money::{Money, Rate};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BalanceType {
+    Purchases,
+    CashAdvances,
+    BalanceTransfers,
+    /// Any other balance, such as a promotional offer, as printed.
+    Other(String),
+}
+
+impl BalanceType {
+    fn classify(printed: &str) -> BalanceType {
+        let lowercase = printed.to_lowercase();
+        if lowercase.starts_with("purchase") {
+            BalanceType::Purchases
+        } else if lowercase.starts_with("cash advance") {
+            BalanceType::CashAdvances
+        } else if lowercase.starts_with("balance transfer") {
+            BalanceType::BalanceTransfers
+        } else {
+            BalanceType::Other(printed.to_string())
+        }
+    }
+}
+
+/// A row of the interest charge calculation table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Apr {
+    pub balance_type: BalanceType,
+    /// The annual percentage rate, in percent.
+    pub rate: Rate,
+    pub balance_subject_to_interest: Money,
+    pub interest_charge: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CreditTerms {
+    pub payment_due_date: Option<Date>,
+    pub minimum_payment: Option<Money>,
+    pub credit_line: Option<Money>,
+    pub available_credit: Option<Money>,
+    pub cash_access_line: Option<Money>,
+    /// The late fee the late payment warning says may be charged.
+    pub late_fee: Option<Money>,
+    pub aprs: Vec<Apr>,
+}
+
+/// An amount in the interest charge table, where Chase prints zero as `- 0 -`.
+fn apr_amount(input: &str) -> PResult<'_, Money> {
+    alt((dollar_amount, value(Money::usd(0), tag("- 0 -"))))(input)
+}
+
+/// A rate such as `19.99%(v)(d)` or `18.24% V`, ignoring the annotations that
+/// mark variable rates and the like.
+fn apr_rate(input: &str) -> PResult<'_, Rate> {
+    let (input, rate) = terminated(rate, char('%'))(input)?;
+    let (input, _) = many0(alt((
+        recognize(delimited(char('('), alpha1, char(')'))),
+        recognize(terminated(preceded(space1, one_of("VF")), peek(space1))),
+    )))(input)?;
+    Ok((input, rate))
+}
+
+fn apr_row(input: &str) -> PResult<'_, Apr> {
+    let (input, (balance_type_chars, rate)) =
+        many_till(anychar, preceded(space1, apr_rate))(input)?;
+    let (input, balance_subject_to_interest) = preceded(multispace1, apr_amount)(input)?;
+    let (input, interest_charge) = preceded(multispace1, apr_amount)(input)?;
+    let balance_type: String = balance_type_chars.into_iter().collect();
+    Ok((
+        input,
+        Apr {
+            balance_type: BalanceType::classify(balance_type.trim()),
+            rate,
+            balance_subject_to_interest,
+            interest_charge,
+        },
+    ))
+}
+
+/// Reads the rows of the interest charge table that follows `header`. Rows
+/// are recognized line by line, so headings between them are skipped; the
+/// table ends at the first blank line after a row.
+pub(crate) fn apr_table<'a>(
+    input: &'a str,
+    header: &str,
+) -> Result<Vec<Apr>, nom::Err<GrammarError<'a>>> {
+    let (table, found) = opt(take_until_including(header))(input)?;
+    if found.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut aprs = Vec::new();
+    for line in table.lines() {
+        let line = line.trim();
+        if line.is_empty() && !aprs.is_empty() {
+            break;
+        }
+        if let (_, Some(apr)) = opt(apr_row)(line)? {
+            aprs.push(apr);
+        }
+    }
+    Ok(aprs)
+}
@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use crate::bank_of_america_credit_statement::BankOfAmericaCreditStatement;
+use crate::bank_of_america_debit_statement::BankOfAmericaDebitStatement;
+use crate::chase_credit_statement::ChaseCreditStatement;
+use crate::error::{ExtractionError, StatementError};
+use crate::extract::{PdfExtract, Pdftotext, TextExtractor};
+use crate::statement::Statement;
+use crate::statement_format::{ParseOptions, StatementFormat};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    BankOfAmericaCredit,
+    BankOfAmericaDebit,
+    ChaseCredit,
+}
+
+/// Phrases that each format prints, in the order its parser looks for them.
+/// Whitespace in both the phrases and the text is collapsed before matching,
+/// so the same fingerprints work for pdf-extract and pdftotext output.
+const FINGERPRINTS: &[(Format, &[&str])] = &[
+    (
+        Format::ChaseCredit,
+        &[
+            "ACCOUNT SUMMARY",
+            "Account Number:",
+            "Opening/Closing Date",
+            "ACCOUNT ACTIVITY",
+        ],
+    ),
+    (
+        Format::BankOfAmericaCredit,
+        &[
+            "Account# ",
+            "Previous Balance",
+            "New Balance Total",
+            "TOTAL INTEREST CHARGED FOR THIS PERIOD",
+        ],
+    ),
+    (
+        Format::BankOfAmericaDebit,
+        &[
+            "Account number:",
+            "Beginning balance on",
+            "Ending balance on",
+            "Deposits and other additions",
+        ],
+    ),
+];
+
+/// The smallest fraction of a format's fingerprints that must be found for
+/// the text to count as that format. A phrase or two in common, such as an
+/// account number label, isn't enough.
+const MIN_CONFIDENCE: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Detection {
+    pub format: Format,
+    /// Fraction of the format's fingerprints found in the text, in `0.0..=1.0`.
+    pub confidence: f32,
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Scores every known format against `text` and returns the best match, or
+/// `None` if no format reaches `MIN_CONFIDENCE`.
+pub fn detect_format(text: &str) -> Option<Detection> {
+    let text = collapse_whitespace(text);
+    FINGERPRINTS
+        .iter()
+        .map(|(format, markers)| {
+            let found = markers
+                .iter()
+                .filter(|marker| text.contains(&collapse_whitespace(marker)))
+                .count();
+            Detection {
+                format: *format,
+                confidence: found as f32 / markers.len() as f32,
+            }
+        })
+        .filter(|detection| detection.confidence >= MIN_CONFIDENCE)
+        .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+}
+
+/// The extractors `parse_any` can detect with, so it can tell whether the
+/// detected format wants the text it already has.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Extractor {
+    PdfExtract,
+    PdftotextLayout,
+}
+
+impl Extractor {
+    /// Tried in this order until the text matches a format.
+    const DETECTION_ORDER: [Extractor; 2] = [Extractor::PdfExtract, Extractor::PdftotextLayout];
+
+    fn get(self) -> Box<dyn TextExtractor> {
+        match self {
+            Extractor::PdfExtract => Box::new(PdfExtract),
+            Extractor::PdftotextLayout => Box::new(Pdftotext::layout()),
+        }
+    }
+}
+
+impl Format {
+    /// The extractor each format's grammar is written against. Formats
+    /// return it from `StatementFormat::preferred_extractor`, so this is the
+    /// one place it is decided.
+    fn extractor(self) -> Extractor {
+        match self {
+            Format::BankOfAmericaCredit | Format::BankOfAmericaDebit => Extractor::PdfExtract,
+            Format::ChaseCredit => Extractor::PdftotextLayout,
+        }
+    }
+
+    pub fn preferred_extractor(self) -> Box<dyn TextExtractor> {
+        self.extractor().get()
+    }
+
+    pub fn parse_text(self, text: &str) -> Result<Statement, StatementError> {
+        self.parse_text_with_options(text, &ParseOptions::default())
+    }
+
+    pub fn parse_text_with_options(
+        self,
+        text: &str,
+        options: &ParseOptions,
+    ) -> Result<Statement, StatementError> {
+        Ok(match self {
+            Format::BankOfAmericaCredit => {
+                BankOfAmericaCreditStatement::parse_text_with_options(text, options)?.into()
+            }
+            Format::BankOfAmericaDebit => {
+                BankOfAmericaDebitStatement::parse_text_with_options(text, options)?.into()
+            }
+            Format::ChaseCredit => {
+                ChaseCreditStatement::parse_text_with_options(text, options)?.into()
+            }
+        })
+    }
+
+    pub fn parse_bytes(self, bytes: &[u8]) -> Result<Statement, StatementError> {
+        self.parse_bytes_with_options(bytes, &ParseOptions::default())
+    }
+
+    /// Parses a statement PDF held in memory, extracting its text with the
+    /// format's preferred extractor.
+    pub fn parse_bytes_with_options(
+        self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Statement, StatementError> {
+        self.parse_text_with_options(&self.extractor().get().extract_bytes(bytes)?, options)
+    }
+
+    pub fn parse_file(self, path: &Path) -> Result<Statement, StatementError> {
+        self.parse_file_with_options(path, &ParseOptions::default())
+    }
+
+    /// Parses the statement PDF at `path`, extracting its text with the
+    /// format's preferred extractor.
+    pub fn parse_file_with_options(
+        self,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Statement, StatementError> {
+        self.parse_text_with_options(&self.extractor().get().extract_file(path)?, options)
+    }
+}
+
+/// Extracts text with each detection extractor in turn until it matches a
+/// format, then parses it, extracting again only if the format prefers the
+/// other extractor. If no text matches and an extractor failed, that failure
+/// is returned rather than `UnknownFormat`, since it may be why. The text
+/// that is parsed is handed to `inspect` first.
+fn parse_detected(
+    extract: impl Fn(&dyn TextExtractor) -> Result<String, ExtractionError>,
+    options: &ParseOptions,
+    inspect: impl Fn(&str),
+) -> Result<Statement, StatementError> {
+    let mut error = None;
+    for extractor in Extractor::DETECTION_ORDER {
+        let text = match extract(extractor.get().as_ref()) {
+            Ok(text) => text,
+            Err(e) => {
+                error.get_or_insert(e);
+                continue;
+            }
+        };
+        let Some(detection) = detect_format(&text) else {
+            continue;
+        };
+        let format = detection.format;
+        let text = if format.extractor() == extractor {
+            text
+        } else {
+            extract(format.extractor().get().as_ref())?
+        };
+        inspect(&text);
+        return format.parse_text_with_options(&text, options);
+    }
+    Err(error.map_or(StatementError::UnknownFormat, StatementError::from))
+}
+
+/// Detects the format of the statement at `path` and parses it.
+pub fn parse_any(path: &Path) -> Result<Statement, StatementError> {
+    parse_any_with_options(path, &ParseOptions::default())
+}
+
+pub fn parse_any_with_options(
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<Statement, StatementError> {
+    parse_detected(|extractor| extractor.extract_file(path), options, |_| {})
+}
+
+/// Like `parse_any_with_options`, handing the text the detected format
+/// parses to `inspect` first, e.g. to print it while debugging.
+pub fn parse_any_inspect(
+    path: &Path,
+    options: &ParseOptions,
+    inspect: impl Fn(&str),
+) -> Result<Statement, StatementError> {
+    parse_detected(|extractor| extractor.extract_file(path), options, inspect)
+}
+
+/// Detects the format of the statement PDF in `bytes` and parses it.
+pub fn parse_any_bytes(bytes: &[u8]) -> Result<Statement, StatementError> {
+    parse_any_bytes_with_options(bytes, &ParseOptions::default())
+}
+
+pub fn parse_any_bytes_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<Statement, StatementError> {
+    parse_detected(|extractor| extractor.extract_bytes(bytes), options, |_| {})
+}
+
+/// Like `parse_any_bytes_with_options`, handing the text the detected format
+/// parses to `inspect` first.
+pub fn parse_any_bytes_inspect(
+    bytes: &[u8],
+    options: &ParseOptions,
+    inspect: impl Fn(&str),
+) -> Result<Statement, StatementError> {
+    parse_detected(|extractor| extractor.extract_bytes(bytes), options, inspect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdftotext::PdftotextError;
+
+    #[test]
+    fn detects_format_from_fingerprints() {
+        let text = "ACCOUNT SUMMARY\nAccount Number:  XXXX 1234\nOpening/Closing   Date 04/02/23";
+        let detection = detect_format(text).unwrap();
+        assert_eq!(detection.format, Format::ChaseCredit);
+        assert_eq!(detection.confidence, 0.75);
+    }
+
+    #[test]
+    fn reports_failed_fallback_extraction() {
+        let calls = std::cell::Cell::new(0);
+        let result = parse_detected(
+            |_| {
+                calls.set(calls.get() + 1);
+                match calls.get() {
+                    1 => Ok("not a statement".to_string()),
+                    _ => Err(ExtractionError::Pdftotext(PdftotextError::NotInstalled)),
+                }
+            },
+            &ParseOptions::default(),
+            |_| {},
+        );
+        assert!(matches!(
+            result,
+            Err(StatementError::Extraction(ExtractionError::Pdftotext(
+                PdftotextError::NotInstalled
+            )))
+        ));
+    }
+
+    #[test]
+    fn one_common_phrase_is_not_a_match() {
+        assert_eq!(detect_format("Previous Balance $12.00"), None);
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+
+use chrono::naive::NaiveDate as Date;
+use nom::error::{ContextError, ErrorKind, FromExternalError};
+use nom::{IResult, Offset};
+
+use crate::money::Money;
+use crate::pdftotext::PdftotextError;
+
+#[derive(Debug)]
+pub enum ExtractionError {
+    PdfExtract(pdf_extract::OutputError),
+    Pdftotext(PdftotextError),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::PdfExtract(e) => write!(f, "pdf-extract failed: {}", e),
+            ExtractionError::Pdftotext(e) => write!(f, "{}", e),
+            ExtractionError::Io(e) => write!(f, "could not read statement: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractionError::PdfExtract(e) => Some(e),
+            ExtractionError::Pdftotext(e) => Some(e),
+            ExtractionError::Io(e) => Some(e),
+        }
+    }
+}
+
+/// Error type threaded through every statement grammar. Unlike
+/// `nom::error::Error` it remembers the `context` labels that were active when
+/// the failure happened, so it can be turned into a `ParseError` that says
+/// which section of the statement was being read.
+#[derive(Debug)]
+pub struct GrammarError<'a> {
+    pub input: &'a str,
+    pub kind: ErrorKind,
+    pub context: Vec<&'static str>,
+    pub reconciliation: Option<Box<ReconciliationError>>,
+}
+
+pub type PResult<'a, O> = IResult<&'a str, O, GrammarError<'a>>;
+
+impl<'a> nom::error::ParseError<&'a str> for GrammarError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        GrammarError {
+            input,
+            kind,
+            context: Vec::new(),
+            reconciliation: None,
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        // Keep the innermost failure; it is the one closest to the bad text.
+        other
+    }
+}
+
+impl<'a> ContextError<&'a str> for GrammarError<'a> {
+    fn add_context(_input: &'a str, ctx: &'static str, mut other: Self) -> Self {
+        other.context.push(ctx);
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for GrammarError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        nom::error::ParseError::from_error_kind(input, kind)
+    }
+}
+
+const EXCERPT_LENGTH: usize = 80;
+
+/// A grammar failure, located in the extracted text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    /// Byte offset of the failure in the extracted text.
+    pub offset: usize,
+    /// 1-based line of the failure in the extracted text.
+    pub line: usize,
+    /// 1-based column (in characters) of the failure in the extracted text.
+    pub column: usize,
+    /// The innermost statement section being read, e.g. "Service fees".
+    pub section: Option<String>,
+    /// The extracted text from the failure to the end of its line, truncated.
+    pub excerpt: String,
+}
+
+impl ParseError {
+    fn new(text: &str, error: GrammarError) -> Self {
+        // `error.input` is usually the rest of `text`, but may be a single
+        // line of it, so locate it by position rather than by length.
+        let offset = text.offset(error.input);
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = text[..offset].matches('\n').count() + 1;
+        let column = text[line_start..offset].chars().count() + 1;
+        let excerpt = error
+            .input
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim_end()
+            .chars()
+            .take(EXCERPT_LENGTH)
+            .collect();
+        ParseError {
+            kind: error.kind,
+            offset,
+            line,
+            column,
+            section: error.context.first().map(|s| s.trim().to_string()),
+            excerpt,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at line {}, column {}",
+            self.kind, self.line, self.column
+        )?;
+        if let Some(section) = &self.section {
+            write!(f, " in section \"{}\"", section)?;
+        }
+        write!(f, ": {:?}", self.excerpt)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A transaction as it was counted towards a total that failed to reconcile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciledTransaction {
+    pub date: Date,
+    pub description: String,
+    pub amount: Money,
+}
+
+/// A printed total that did not match the sum of the parsed transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciliationError {
+    /// The section whose total failed, e.g. "Withdrawals and other subtractions".
+    pub section: String,
+    /// The total printed on the statement.
+    pub expected: Money,
+    /// The total computed from the parsed transactions.
+    pub computed: Money,
+    /// For day-by-day balance checks, the first day whose printed balance
+    /// did not match; `expected` and `computed` are then balances.
+    pub date: Option<Date>,
+    /// The transactions that went into `computed`, or for a day-by-day
+    /// balance check, the transactions on `date`.
+    pub transactions: Vec<ReconciledTransaction>,
+}
+
+impl ReconciliationError {
+    /// How much the parsed transactions are short of the printed total, or
+    /// `None` if that overflows.
+    pub fn difference(&self) -> Option<Money> {
+        self.expected.checked_sub(self.computed)
+    }
+}
+
+impl fmt::Display for ReconciliationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.date {
+            Some(date) => write!(
+                f,
+                "section \"{}\" expected a balance of {} on {} but the transactions through \
+                 that day leave {}",
+                self.section, self.expected, date, self.computed
+            )?,
+            None => write!(
+                f,
+                "section \"{}\" expected {} but its {} transactions add up to {}",
+                self.section,
+                self.expected,
+                self.transactions.len(),
+                self.computed
+            )?,
+        }
+        if let Some(difference) = self.difference() {
+            write!(f, " (difference {})", difference)?;
+        }
+        for transaction in &self.transactions {
+            write!(
+                f,
+                "\n  {} {:?} {}",
+                transaction.date, transaction.description, transaction.amount
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ReconciliationError {}
+
+fn reconciliation_failure(
+    input: &str,
+    reconciliation: ReconciliationError,
+) -> nom::Err<GrammarError<'_>> {
+    nom::Err::Failure(GrammarError {
+        input,
+        kind: ErrorKind::Verify,
+        context: Vec::new(),
+        reconciliation: Some(Box::new(reconciliation)),
+    })
+}
+
+/// Checks a printed total against the one computed from the parsed
+/// transactions, failing the whole parse if they differ.
+pub(crate) fn verify_total<'a>(
+    input: &'a str,
+    section: &str,
+    expected: Money,
+    computed: Money,
+    transactions: impl FnOnce() -> Vec<ReconciledTransaction>,
+) -> Result<(), nom::Err<GrammarError<'a>>> {
+    if expected == computed {
+        return Ok(());
+    }
+    Err(reconciliation_failure(
+        input,
+        ReconciliationError {
+            section: section.trim().to_string(),
+            expected,
+            computed,
+            date: None,
+            transactions: transactions(),
+        },
+    ))
+}
+
+/// Checks the balance printed for `date` against the one computed by
+/// applying the parsed transactions through that day.
+pub(crate) fn verify_balance_on<'a>(
+    input: &'a str,
+    section: &str,
+    date: Date,
+    expected: Money,
+    computed: Money,
+    transactions: impl FnOnce() -> Vec<ReconciledTransaction>,
+) -> Result<(), nom::Err<GrammarError<'a>>> {
+    if expected == computed {
+        return Ok(());
+    }
+    Err(reconciliation_failure(
+        input,
+        ReconciliationError {
+            section: section.trim().to_string(),
+            expected,
+            computed,
+            date: Some(date),
+            transactions: transactions(),
+        },
+    ))
+}
+
+#[derive(Debug)]
+pub enum StatementError {
+    /// The text could not be extracted from the PDF.
+    Extraction(ExtractionError),
+    /// The extracted text did not match the statement grammar.
+    Parse(ParseError),
+    /// The statement parsed, but the totals or balances did not add up.
+    Reconciliation(ReconciliationError),
+    /// The text did not look like any supported statement format.
+    UnknownFormat,
+}
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementError::Extraction(e) => write!(f, "text extraction failed: {}", e),
+            StatementError::Parse(e) => write!(f, "statement did not parse: {}", e),
+            StatementError::Reconciliation(e) => write!(f, "statement did not reconcile: {}", e),
+            StatementError::UnknownFormat => write!(f, "not a supported statement format"),
+        }
+    }
+}
+
+impl std::error::Error for StatementError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StatementError::Extraction(e) => Some(e),
+            StatementError::Parse(e) => Some(e),
+            StatementError::Reconciliation(e) => Some(e),
+            StatementError::UnknownFormat => None,
+        }
+    }
+}
+
+impl From<ExtractionError> for StatementError {
+    fn from(e: ExtractionError) -> Self {
+        StatementError::Extraction(e)
+    }
+}
+
+/// Converts the result of running a statement grammar over `text` into a
+/// `StatementError` that no longer borrows from `text`.
+pub(crate) fn finish<'a, O>(text: &'a str, result: PResult<'a, O>) -> Result<O, StatementError> {
+    match result {
+        Ok((_, output)) => Ok(output),
+        Err(nom::Err::Error(mut error) | nom::Err::Failure(mut error)) => {
+            match error.reconciliation.take() {
+                Some(reconciliation) => Err(StatementError::Reconciliation(*reconciliation)),
+                None => Err(StatementError::Parse(ParseError::new(text, error))),
+            }
+        }
+        Err(nom::Err::Incomplete(_)) => Err(StatementError::Parse(ParseError::new(
+            text,
+            nom::error::ParseError::from_error_kind(&text[text.len()..], ErrorKind::Eof),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use nom::error::context;
+
+    use super::*;
+    use crate::common_parsers::{dollar_amount, take_until_including};
+
+    fn new_balance(input: &str) -> PResult<'_, Money> {
+        context("Account Summary", |input| {
+            let (input, ()) = take_until_including("New Balance Total ")(input)?;
+            dollar_amount(input)
+        })(input)
+    }
+
+    #[test]
+    fn locates_parse_error() {
+        let text = "Account Summary\nPrevious Balance $10.00\nNew Balance Total $12.3 due\n";
+        let Err(StatementError::Parse(error)) = finish(text, new_balance(text)) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.line, 3);
+        assert_eq!(error.column, 23);
+        assert_eq!(error.offset, text.find("3 due").unwrap());
+        assert_eq!(error.section.as_deref(), Some("Account Summary"));
+        assert_eq!(error.excerpt, "3 due");
+    }
+
+    #[test]
+    fn parse_error_is_the_source() {
+        let text = "New Balance Total due";
+        let error = finish(text, new_balance(text)).unwrap_err();
+        let source = error.source().expect("a source");
+        assert!(source.downcast_ref::<ParseError>().is_some());
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use crate::error::ExtractionError;
+use crate::pdftotext::{pdftotext, pdftotext_bytes, PdftotextMode, PdftotextOptions};
+
+/// A way of turning a statement PDF into the text the grammars run over.
+pub trait TextExtractor {
+    fn extract_file(&self, path: &Path) -> Result<String, ExtractionError>;
+    fn extract_bytes(&self, bytes: &[u8]) -> Result<String, ExtractionError>;
+}
+
+impl<T: TextExtractor + ?Sized> TextExtractor for Box<T> {
+    fn extract_file(&self, path: &Path) -> Result<String, ExtractionError> {
+        (**self).extract_file(path)
+    }
+
+    fn extract_bytes(&self, bytes: &[u8]) -> Result<String, ExtractionError> {
+        (**self).extract_bytes(bytes)
+    }
+}
+
+/// Extracts text in-process with the `pdf-extract` crate.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PdfExtract;
+
+impl TextExtractor for PdfExtract {
+    fn extract_file(&self, path: &Path) -> Result<String, ExtractionError> {
+        pdf_extract::extract_text(path).map_err(ExtractionError::PdfExtract)
+    }
+
+    fn extract_bytes(&self, bytes: &[u8]) -> Result<String, ExtractionError> {
+        pdf_extract::extract_text_from_mem(bytes).map_err(ExtractionError::PdfExtract)
+    }
+}
+
+/// Extracts text with poppler's `pdftotext` binary, which must be on `PATH`.
+#[derive(Debug, Clone, Default)]
+pub struct Pdftotext {
+    pub options: PdftotextOptions,
+}
+
+impl Pdftotext {
+    /// Keeps the physical layout of the page (`-layout`).
+    pub fn layout() -> Self {
+        Pdftotext {
+            options: PdftotextOptions {
+                mode: PdftotextMode::Layout,
+                ..PdftotextOptions::default()
+            },
+        }
+    }
+
+    /// Emits text in content stream order (`-raw`).
+    pub fn raw() -> Self {
+        Pdftotext {
+            options: PdftotextOptions {
+                mode: PdftotextMode::Raw,
+                ..PdftotextOptions::default()
+            },
+        }
+    }
+}
+
+impl TextExtractor for Pdftotext {
+    fn extract_file(&self, path: &Path) -> Result<String, ExtractionError> {
+        pdftotext(path, &self.options).map_err(ExtractionError::Pdftotext)
+    }
+
+    fn extract_bytes(&self, bytes: &[u8]) -> Result<String, ExtractionError> {
+        pdftotext_bytes(bytes, &self.options).map_err(ExtractionError::Pdftotext)
+    }
+}
+
+/// Returns text that was extracted ahead of time, whatever PDF it is given.
+#[derive(Debug, Clone, Default)]
+pub struct PreExtracted(pub String);
+
+impl TextExtractor for PreExtracted {
+    fn extract_file(&self, _path: &Path) -> Result<String, ExtractionError> {
+        Ok(self.0.clone())
+    }
+
+    fn extract_bytes(&self, _bytes: &[u8]) -> Result<String, ExtractionError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Wraps another extractor and hands every extracted text to `callback`
+/// before it is parsed. The library never prints statement text itself, so
+/// this is the place to hook in logging while debugging a layout change.
+pub struct Inspect<E, F> {
+    extractor: E,
+    callback: F,
+}
+
+impl<E: TextExtractor, F: Fn(&str)> Inspect<E, F> {
+    pub fn new(extractor: E, callback: F) -> Self {
+        Inspect {
+            extractor,
+            callback,
+        }
+    }
+}
+
+impl<E: TextExtractor, F: Fn(&str)> TextExtractor for Inspect<E, F> {
+    fn extract_file(&self, path: &Path) -> Result<String, ExtractionError> {
+        let text = self.extractor.extract_file(path)?;
+        (self.callback)(&text);
+        Ok(text)
+    }
+
+    fn extract_bytes(&self, bytes: &[u8]) -> Result<String, ExtractionError> {
+        let text = self.extractor.extract_bytes(bytes)?;
+        (self.callback)(&text);
+        Ok(text)
+    }
+}
@@ -0,0 +1,176 @@
+//! The line card statements print under a purchase made in another currency,
+//! e.g. "EURO 45.00 X 1.0923 (EXCHG RATE)".
+
+use chrono::naive::NaiveDate as Date;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alpha1, char, space1},
+    combinator::{map, recognize},
+    multi::separated_list1,
+    sequence::{delimited, preceded, tuple},
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::common_parsers::*;
+use crate::error::PResult;
+use crate::money::{Currency, Money, Rate};
+
+/// Currency names as issuers print them, with their ISO 4217 codes.
+const CURRENCY_NAMES: [(&str, &str); 19] = [
+    ("EURO", "EUR"),
+    ("POUND STERLING", "GBP"),
+    ("BRITISH POUND", "GBP"),
+    ("CANADIAN DOLLAR", "CAD"),
+    ("MEXICAN PESO", "MXN"),
+    ("JAPANESE YEN", "JPY"),
+    ("YEN", "JPY"),
+    ("SWISS FRANC", "CHF"),
+    ("AUSTRALIAN DOLLAR", "AUD"),
+    ("NEW ZEALAND DOLLAR", "NZD"),
+    ("HONG KONG DOLLAR", "HKD"),
+    ("SINGAPORE DOLLAR", "SGD"),
+    ("INDIAN RUPEE", "INR"),
+    ("CHINESE YUAN", "CNY"),
+    ("YUAN RENMINBI", "CNY"),
+    ("SWEDISH KRONA", "SEK"),
+    ("NORWEGIAN KRONE", "NOK"),
+    ("DANISH KRONE", "DKK"),
+    ("KOREAN WON", "KRW"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForeignExchange {
+    /// The amount charged in the foreign currency, in hundredths of its main
+    /// unit.
+    pub original_cents: i64,
+    /// The currency as printed, e.g. "EURO".
+    pub currency: String,
+    /// The ISO 4217 code for `currency`, if it is one this crate knows.
+    pub currency_code: Option<Currency>,
+    /// Dollars per unit of the foreign currency.
+    pub rate: Rate,
+}
+
+impl ForeignExchange {
+    /// The amount charged in the foreign currency, if its code is known.
+    pub fn original_amount(&self) -> Option<Money> {
+        Some(Money::new(self.original_cents, self.currency_code?))
+    }
+}
+
+/// Maps a printed currency name, or a code printed as is, to its code.
+fn currency_code(name: &str) -> Option<Currency> {
+    CURRENCY_NAMES
+        .iter()
+        .find(|(printed, _)| *printed == name)
+        .and_then(|(_, code)| Currency::new(code))
+        .or_else(|| Currency::new(name))
+}
+
+/// An exchange line such as "EURO 45.00 X 1.0923 (EXCHG RATE)", in any
+/// currency. `currency_code` is `None` for names missing from
+/// `CURRENCY_NAMES`.
+pub fn foreign_exchange(input: &str) -> PResult<'_, ForeignExchange> {
+    map(
+        tuple((
+            recognize(separated_list1(char(' '), alpha1)),
+            preceded(space1, whole_dollar_amount),
+            delimited(
+                delimited(space1, char('X'), space1),
+                rate,
+                preceded(space1, tag("(EXCHG RATE)")),
+            ),
+        )),
+        |(name, amount, rate): (&str, Money, Rate)| ForeignExchange {
+            original_cents: amount.cents(),
+            currency: name.to_string(),
+            currency_code: currency_code(name),
+            rate,
+        },
+    )(input)
+}
+
+/// For each transaction, the index of the foreign transaction fee charged for
+/// it: the first fee row on the same date that isn't already linked to an
+/// earlier foreign transaction.
+pub(crate) fn foreign_transaction_fees<T>(
+    transactions: &[T],
+    date: impl Fn(&T) -> Date,
+    is_foreign: impl Fn(&T) -> bool,
+    is_foreign_fee: impl Fn(&T) -> bool,
+) -> Vec<Option<usize>> {
+    let mut linked = vec![false; transactions.len()];
+    transactions
+        .iter()
+        .map(|transaction| {
+            if !is_foreign(transaction) {
+                return None;
+            }
+            let fee = (0..transactions.len()).find(|&i| {
+                !linked[i]
+                    && is_foreign_fee(&transactions[i])
+                    && date(&transactions[i]) == date(transaction)
+            })?;
+            linked[fee] = true;
+            Some(fee)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exchange_line() {
+        let (rest, exchange) = foreign_exchange("EURO 45.00 X 1.0923 (EXCHG RATE)").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            exchange,
+            ForeignExchange {
+                original_cents: 4500,
+                currency: "EURO".into(),
+                currency_code: Currency::new("EUR"),
+                rate: Rate::new(10923, 4),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_multi_word_currency_names() {
+        let (_, exchange) = foreign_exchange("POUND STERLING 1,200 X 1.27 (EXCHG RATE)").unwrap();
+        assert_eq!(
+            exchange.original_amount(),
+            Some(Money::new(120000, Currency::new("GBP").unwrap()))
+        );
+    }
+
+    #[test]
+    fn parses_unknown_currency() {
+        let (rest, exchange) = foreign_exchange("THAI BAHT 700.00 X 0.0285 (EXCHG RATE)").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(exchange.currency, "THAI BAHT");
+        assert_eq!(exchange.currency_code, None);
+        assert_eq!(exchange.original_cents, 70000);
+        assert_eq!(exchange.original_amount(), None);
+        assert_eq!(exchange.rate, Rate::new(285, 4));
+    }
+
+    #[test]
+    fn links_each_fee_once() {
+        let day = |d| Date::from_ymd_opt(2023, 5, d).unwrap();
+        // (date, foreign, foreign fee)
+        let transactions = [
+            (day(1), true, false),
+            (day(1), true, false),
+            (day(1), false, true),
+            (day(2), true, false),
+        ];
+        assert_eq!(
+            foreign_transaction_fees(&transactions, |t| t.0, |t| t.1, |t| t.2),
+            vec![Some(2), None, None, None]
+        );
+    }
+}
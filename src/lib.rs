@@ -2,5 +2,14 @@ pub mod bank_of_america_credit_statement;
 pub mod bank_of_america_debit_statement;
 pub mod chase_credit_statement;
 pub mod common_parsers;
+pub mod credit_terms;
+pub mod detect;
+pub mod error;
+pub mod extract;
+pub mod foreign_exchange;
+pub mod money;
 pub mod pdftotext;
+pub mod rewards;
+pub mod statement;
 pub mod statement_format;
+pub mod year_to_date;
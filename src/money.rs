@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An ISO 4217 currency code, e.g. `USD`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+
+    /// Returns `None` unless `code` is three ASCII uppercase letters.
+    pub fn new(code: &str) -> Option<Currency> {
+        let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+        if bytes.iter().all(u8::is_ascii_uppercase) {
+            Some(Currency(bytes))
+        } else {
+            None
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        // Only ever built from ASCII uppercase letters.
+        std::str::from_utf8(&self.0).unwrap()
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Currency").field(&self.code()).finish()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::new(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid currency code {:?}", code)))
+    }
+}
+
+/// An amount of money, stored as a whole number of cents (hundredths of the
+/// currency's main unit). Arithmetic is checked: it returns `None` on
+/// overflow or when the currencies differ. Displays as `-$1,234.56` for
+/// dollars and `-1,234.56 EUR` for other currencies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Money {
+    cents: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(cents: i64, currency: Currency) -> Self {
+        Money { cents, currency }
+    }
+
+    pub fn usd(cents: i64) -> Self {
+        Money::new(cents, Currency::USD)
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Money::new(0, currency)
+    }
+
+    pub fn cents(&self) -> i64 {
+        self.cents
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.cents < 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Money::new(
+            self.cents.checked_add(other.cents)?,
+            self.currency,
+        ))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Money::new(
+            self.cents.checked_sub(other.cents)?,
+            self.currency,
+        ))
+    }
+
+    pub fn checked_neg(self) -> Option<Money> {
+        Some(Money::new(self.cents.checked_neg()?, self.currency))
+    }
+
+    /// Negates the amount, turning the one amount that can't be negated,
+    /// `i64::MIN` cents, into `i64::MAX` cents.
+    pub fn saturating_neg(self) -> Money {
+        Money::new(self.cents.saturating_neg(), self.currency)
+    }
+
+    /// Adds up `amounts`, starting from zero in `currency`.
+    pub fn checked_sum(
+        currency: Currency,
+        amounts: impl IntoIterator<Item = Money>,
+    ) -> Option<Money> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), Money::checked_add)
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(self.cents.cmp(&other.cents))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let cents = self.cents.unsigned_abs();
+        let digits = (cents / 100).to_string();
+        let mut dollars = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                dollars.push(',');
+            }
+            dollars.push(digit);
+        }
+        if self.currency == Currency::USD {
+            write!(f, "{}${}.{:02}", sign, dollars, cents % 100)
+        } else {
+            write!(
+                f,
+                "{}{}.{:02} {}",
+                sign,
+                dollars,
+                cents % 100,
+                self.currency
+            )
+        }
+    }
+}
+
+/// A decimal number kept exactly as printed, such as an APR of `19.99`
+/// (percent) or an exchange rate of `1.1218`. The value is
+/// `units / 10^scale`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rate {
+    units: i64,
+    scale: u32,
+}
+
+impl Rate {
+    pub fn new(units: i64, scale: u32) -> Self {
+        Rate { units, scale }
+    }
+
+    pub fn units(&self) -> i64 {
+        self.units
+    }
+
+    /// How many of the digits in `units` are after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0width$}",
+            self.units.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (whole, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_dollars_with_separators() {
+        assert_eq!(Money::usd(123456).to_string(), "$1,234.56");
+        assert_eq!(Money::usd(-5).to_string(), "-$0.05");
+        assert_eq!(Money::usd(100000000).to_string(), "$1,000,000.00");
+    }
+
+    #[test]
+    fn displays_other_currencies_by_code() {
+        let euro = Currency::new("EUR").unwrap();
+        assert_eq!(Money::new(-4500, euro).to_string(), "-45.00 EUR");
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        let euro = Currency::new("EUR").unwrap();
+        assert_eq!(Money::usd(i64::MAX).checked_add(Money::usd(1)), None);
+        assert_eq!(Money::usd(i64::MIN).checked_neg(), None);
+        assert_eq!(Money::usd(i64::MIN).saturating_neg(), Money::usd(i64::MAX));
+        assert_eq!(Money::usd(1).checked_add(Money::new(1, euro)), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_invalid_currency() {
+        assert!(serde_json::from_str::<Currency>(r#""usd""#).is_err());
+    }
+
+    #[test]
+    fn displays_rates_as_printed() {
+        assert_eq!(Rate::new(11218, 4).to_string(), "1.1218");
+        assert_eq!(Rate::new(5, 2).to_string(), "0.05");
+        assert_eq!(Rate::new(19, 0).to_string(), "19");
+    }
+}
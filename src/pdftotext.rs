@@ -1,17 +1,180 @@
-use std::fs::read_to_string;
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use tempfile::NamedTempFile;
 
-pub fn pdftotext(path: &Path, layout: bool) -> std::io::Result<String> {
-    let temp_file = NamedTempFile::new()?;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+pub enum PdftotextError {
+    /// The `pdftotext` binary is not on `PATH`; install poppler-utils.
+    NotInstalled,
+    Io(io::Error),
+    /// `pdftotext` exited unsuccessfully.
+    Failed {
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// `pdftotext` was killed after running longer than the timeout.
+    TimedOut(Duration),
+    /// The output was not UTF-8; pick an encoding that is, such as `UTF-8`.
+    InvalidUtf8(std::string::FromUtf8Error),
+}
+
+impl fmt::Display for PdftotextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdftotextError::NotInstalled => write!(f, "pdftotext is not installed"),
+            PdftotextError::Io(e) => write!(f, "{}", e),
+            PdftotextError::Failed { status, stderr } => {
+                write!(f, "pdftotext exited with {}: {}", status, stderr.trim())
+            }
+            PdftotextError::TimedOut(timeout) => {
+                write!(f, "pdftotext did not finish within {:?}", timeout)
+            }
+            PdftotextError::InvalidUtf8(e) => write!(f, "pdftotext output is not UTF-8: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PdftotextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PdftotextError::Io(e) => Some(e),
+            PdftotextError::InvalidUtf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PdftotextError {
+    fn from(e: io::Error) -> Self {
+        PdftotextError::Io(e)
+    }
+}
+
+/// How `pdftotext` orders the text it extracts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PdftotextMode {
+    /// Reading order, `pdftotext`'s own default.
+    #[default]
+    Default,
+    /// Keep the physical layout of the page (`-layout`).
+    Layout,
+    /// Content stream order (`-raw`).
+    Raw,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdftotextOptions {
+    pub mode: PdftotextMode,
+    /// First page to convert (`-f`), 1-based.
+    pub first_page: Option<u32>,
+    /// Last page to convert (`-l`), 1-based and inclusive.
+    pub last_page: Option<u32>,
+    /// Output text encoding (`-enc`). The output must still decode as UTF-8.
+    pub encoding: Option<String>,
+    /// Kill `pdftotext` if it runs longer than this.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PdftotextOptions {
+    fn default() -> Self {
+        PdftotextOptions {
+            mode: PdftotextMode::Default,
+            first_page: None,
+            last_page: None,
+            encoding: None,
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    mut reader: R,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus, PdftotextError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok(child.wait()?),
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(PdftotextError::TimedOut(timeout));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub fn pdftotext(path: &Path, options: &PdftotextOptions) -> Result<String, PdftotextError> {
     let mut command = Command::new("pdftotext");
-    command.arg(path).arg(temp_file.path());
-    if layout {
-        command.arg("-layout");
+    match options.mode {
+        PdftotextMode::Default => {}
+        PdftotextMode::Layout => {
+            command.arg("-layout");
+        }
+        PdftotextMode::Raw => {
+            command.arg("-raw");
+        }
+    }
+    if let Some(first_page) = options.first_page {
+        command.arg("-f").arg(first_page.to_string());
+    }
+    if let Some(last_page) = options.last_page {
+        command.arg("-l").arg(last_page.to_string());
+    }
+    if let Some(encoding) = &options.encoding {
+        command.arg("-enc").arg(encoding);
     }
-    let mut child = command.spawn()?;
-    let _ = child.wait()?;
-    read_to_string(temp_file.path())
+    // "-" sends the text to stdout instead of a file.
+    command
+        .arg(path)
+        .arg("-")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => PdftotextError::NotInstalled,
+        _ => PdftotextError::Io(e),
+    })?;
+    // Drain both pipes while waiting so a chatty child can't fill one and block.
+    let stdout = read_in_background(child.stdout.take().expect("stdout is piped"));
+    let stderr = read_in_background(child.stderr.take().expect("stderr is piped"));
+    let status = wait_with_timeout(&mut child, options.timeout)?;
+    let stdout = stdout.join().expect("stdout reader panicked")?;
+    let stderr = stderr.join().expect("stderr reader panicked")?;
+    if !status.success() {
+        return Err(PdftotextError::Failed {
+            status,
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        });
+    }
+    String::from_utf8(stdout).map_err(PdftotextError::InvalidUtf8)
+}
+
+pub fn pdftotext_bytes(bytes: &[u8], options: &PdftotextOptions) -> Result<String, PdftotextError> {
+    let mut pdf_file = NamedTempFile::new()?;
+    pdf_file.write_all(bytes)?;
+    pdf_file.flush()?;
+    pdftotext(pdf_file.path(), options)
 }
@@ -0,0 +1,206 @@
+//! The rewards block of credit card statements: Chase Ultimate Rewards points
+//! and Bank of America cash back or points. Issuers word the lines
+//! differently from card to card, so each line is classified by its label.
+
+use nom::{
+    branch::alt,
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map, map_res, opt, recognize},
+    multi::separated_list1,
+    sequence::preceded,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::common_parsers::*;
+use crate::error::PResult;
+
+/// How many lines after the header to look for the ending balance.
+const MAX_LINES: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RewardsUnit {
+    Points,
+    /// Cash back, counted in cents.
+    Cash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RewardsEarned {
+    /// The earning line as printed, e.g. "2 Points per $1 earned on dining".
+    pub category: String,
+    pub amount: i64,
+}
+
+/// Amounts are in `unit`: points, or cents of cash back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RewardsSummary {
+    pub unit: RewardsUnit,
+    pub beginning: i64,
+    pub earned: Vec<RewardsEarned>,
+    /// Rewards redeemed, as a positive amount.
+    pub redeemed: i64,
+    /// Adjustments, negative when they took rewards away.
+    pub adjusted: i64,
+    pub ending: i64,
+}
+
+impl RewardsSummary {
+    /// The ending balance the other figures add up to, or `None` if that
+    /// overflows.
+    pub fn computed_ending(&self) -> Option<i64> {
+        self.earned
+            .iter()
+            .try_fold(self.beginning, |total, earned| {
+                total.checked_add(earned.amount)
+            })?
+            .checked_sub(self.redeemed)?
+            .checked_add(self.adjusted)
+    }
+
+    pub fn adds_up(&self) -> bool {
+        self.computed_ending() == Some(self.ending)
+    }
+}
+
+/// A points figure such as `12,431`, or a cash figure such as `-$5.00`.
+fn rewards_amount(input: &str) -> PResult<'_, (RewardsUnit, i64)> {
+    let (input, negative) = opt(char('-'))(input)?;
+    let (input, _) = opt(char('+'))(input)?;
+    let (input, (unit, amount)) = alt((
+        map(preceded(char('$'), whole_dollar_amount), |amount| {
+            (RewardsUnit::Cash, amount.cents())
+        }),
+        map(
+            map_res(
+                recognize(separated_list1(char(','), digit1)),
+                |points: &str| points.replace(',', "").parse::<i64>(),
+            ),
+            |points| (RewardsUnit::Points, points),
+        ),
+    ))(input)?;
+    Ok((
+        input,
+        (unit, if negative.is_some() { -amount } else { amount }),
+    ))
+}
+
+/// Splits a line into its label and the figure at the end of it, if any.
+fn label_and_amount(line: &str) -> Option<(&str, (RewardsUnit, i64))> {
+    let (label, figure) = line.trim().rsplit_once(char::is_whitespace)?;
+    let (_, amount) = all_consuming(rewards_amount)(figure).ok()?;
+    Some((label.trim(), amount))
+}
+
+/// Reads the rewards block that starts after the first of `headers` found in
+/// `input`, up to its ending balance. `None` if there is no such block. A
+/// block that doesn't add up is still returned, since issuers leave lines out
+/// of it; check it with [`RewardsSummary::adds_up`].
+pub(crate) fn rewards_summary(input: &str, headers: &[&str]) -> Option<RewardsSummary> {
+    let (block, ()) = headers
+        .iter()
+        .find_map(|header| take_until_including(header)(input).ok())?;
+
+    let mut beginning = None;
+    let mut earned = Vec::new();
+    let mut redeemed = 0i64;
+    let mut adjusted = 0i64;
+    let mut pending_label = String::new();
+    for line in block.lines().skip(1).take(MAX_LINES) {
+        let Some((label, (line_unit, amount))) = label_and_amount(line) else {
+            // A label wrapped onto the next line, as in "Total points
+            // available for" / "redemption 12,431".
+            pending_label = line.trim().to_string();
+            continue;
+        };
+        let label = if label.starts_with(char::is_lowercase) && !pending_label.is_empty() {
+            format!("{} {}", pending_label, label)
+        } else {
+            label.to_string()
+        };
+        pending_label.clear();
+        // A leading "+" or "-" marks the line as adding or taking away.
+        let (sign, label) = match label.split_once(' ') {
+            Some(("-", rest)) => (-1, rest.trim()),
+            Some(("+", rest)) => (1, rest.trim()),
+            _ => (1, label.as_str()),
+        };
+        // Match the start of a word, so "Pending" isn't taken for "ending"
+        // but "Redemptions" is still taken for a redemption.
+        let lowercase = label.to_lowercase();
+        let word_starts = |prefix: &str| {
+            lowercase
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word.starts_with(prefix))
+        };
+        if word_starts("previous") || word_starts("beginning") {
+            beginning = Some(amount);
+        } else if word_starts("available") || word_starts("ending") {
+            return Some(RewardsSummary {
+                unit: line_unit,
+                beginning: beginning.unwrap_or(0),
+                earned,
+                redeemed,
+                adjusted,
+                ending: amount,
+            });
+        } else if word_starts("redeem") || word_starts("redempt") {
+            redeemed = redeemed.saturating_add(amount.saturating_abs());
+        } else if word_starts("adjust") {
+            adjusted = adjusted.saturating_add(sign * amount);
+        } else if word_starts("earn") || word_starts("bonus") {
+            earned.push(RewardsEarned {
+                category: label.to_string(),
+                amount: sign * amount,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADERS: &[&str] = &["REWARDS SUMMARY"];
+
+    #[test]
+    fn skips_pending_lines() {
+        let text = "REWARDS SUMMARY\n\
+                    Previous points balance 1,000\n\
+                    Pending points 50\n\
+                    + 1 Point per $1 earned on all purchases 200\n\
+                    Total points available for\n\
+                    redemption 1,200\n";
+        let summary = rewards_summary(text, HEADERS).unwrap();
+        assert_eq!(summary.ending, 1200);
+        assert!(summary.adds_up());
+    }
+
+    #[test]
+    fn keeps_summary_that_does_not_add_up() {
+        let text = "REWARDS SUMMARY\n\
+                    Beginning balance $10.00\n\
+                    Cash back earned $2.00\n\
+                    Ending balance $15.00\n";
+        let summary = rewards_summary(text, HEADERS).unwrap();
+        assert_eq!(summary.unit, RewardsUnit::Cash);
+        assert_eq!(summary.computed_ending(), Some(1200));
+        assert!(!summary.adds_up());
+    }
+
+    #[test]
+    fn counts_redemptions() {
+        let text = "REWARDS SUMMARY\n\
+                    Beginning balance 5,000\n\
+                    Points earned on purchases 300\n\
+                    Redemptions -1,000\n\
+                    Ending balance 4,300\n";
+        let summary = rewards_summary(text, HEADERS).unwrap();
+        assert_eq!(summary.redeemed, 1000);
+        assert!(summary.adds_up());
+    }
+}
@@ -0,0 +1,255 @@
+//! Bank-agnostic statement model. Every statement format converts into these
+//! types, so callers that don't care about the issuer only handle one shape.
+//!
+//! Transaction amounts follow one sign convention whatever the issuer prints:
+//! positive amounts increase what the account holder owns (deposits, card
+//! payments and refunds), negative amounts decrease it (withdrawals,
+//! purchases, fees). The amount as printed is kept alongside for auditing.
+
+use chrono::naive::NaiveDate as Date;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::credit_terms::CreditTerms;
+use crate::foreign_exchange::ForeignExchange;
+use crate::money::Money;
+use crate::rewards::RewardsSummary;
+use crate::year_to_date::YearToDate;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Issuer {
+    BankOfAmerica,
+    Chase,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AccountKind {
+    Checking,
+    Credit,
+}
+
+impl AccountKind {
+    /// Converts an amount as the issuer prints it for this kind of account to
+    /// the normalized sign convention. Checking statements already print
+    /// withdrawals as negative; credit card statements print purchases as
+    /// positive because they increase the balance owed. Negation saturates;
+    /// see [`Money::saturating_neg`].
+    pub fn normalize(self, printed: Money) -> Money {
+        match self {
+            AccountKind::Checking => printed,
+            AccountKind::Credit => printed.saturating_neg(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TransactionKind {
+    Deposit,
+    Withdrawal,
+    Credit,
+    Purchase,
+    CashAdvance,
+    BalanceTransfer,
+    Fee,
+    Interest,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FeeKind {
+    MonthlyMaintenance,
+    Overdraft,
+    Atm,
+    Wire,
+    ForeignTransaction,
+    Other,
+}
+
+impl FeeKind {
+    /// Classifies a fee from the description the issuer prints for it.
+    pub fn classify(description: &str) -> FeeKind {
+        let description = description.to_lowercase();
+        let has_word = |word: &str| {
+            description
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|w| w == word)
+        };
+        if description.contains("foreign transaction")
+            || description.contains("international transaction")
+        {
+            FeeKind::ForeignTransaction
+        } else if description.contains("maintenance") {
+            FeeKind::MonthlyMaintenance
+        } else if description.contains("overdraft")
+            || description.contains("returned item")
+            || has_word("nsf")
+        {
+            FeeKind::Overdraft
+        } else if has_word("atm") {
+            FeeKind::Atm
+        } else if has_word("wire") {
+            FeeKind::Wire
+        } else {
+            FeeKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    /// What the fee was for, for `TransactionKind::Fee` transactions.
+    pub fee_kind: Option<FeeKind>,
+    /// The date the transaction happened, as printed on the statement.
+    pub date: Date,
+    /// The date the transaction posted to the account, if printed separately.
+    pub posting_date: Option<Date>,
+    pub description: String,
+    pub reference_number: Option<String>,
+    pub check_number: Option<String>,
+    /// The statement section the transaction was listed under, for issuers
+    /// whose sections say more than `kind` does.
+    pub section: Option<String>,
+    /// The card the transaction was made with, for statements that print it.
+    pub card_number: Option<String>,
+    /// Amount in the normalized sign convention: positive when it increases
+    /// what the account holder owns.
+    pub amount: Money,
+    /// Amount with the sign printed on the statement.
+    pub printed_amount: Money,
+    /// The exchange details, for purchases made in another currency.
+    pub foreign_exchange: Option<ForeignExchange>,
+    /// Index in the statement's `transactions` of the foreign transaction fee
+    /// charged for this transaction.
+    pub foreign_transaction_fee: Option<usize>,
+}
+
+/// Balances are as printed: the amount held for checking accounts and the
+/// amount owed for credit accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Statement {
+    pub issuer: Issuer,
+    pub account_kind: AccountKind,
+    pub account_number: String,
+    pub start_date: Date,
+    pub end_date: Date,
+    pub start_balance: Money,
+    pub end_balance: Money,
+    /// Interest charged for the period, for accounts that report it. Some
+    /// issuers also itemize it as `TransactionKind::Interest` transactions,
+    /// so don't add both.
+    pub total_interest: Option<Money>,
+    /// Due date, limits and APRs, for credit accounts.
+    pub credit_terms: Option<CreditTerms>,
+    /// Fees and interest charged so far this year, for accounts that report
+    /// them; see [`verify_year_to_date`](crate::year_to_date::verify_year_to_date).
+    pub year_to_date: Option<YearToDate>,
+    /// The rewards block, for cards that earn points or cash back.
+    pub rewards: Option<RewardsSummary>,
+    /// End-of-day balances in date order, for statements that print a daily
+    /// balance table.
+    pub daily_balances: Option<Vec<(Date, Money)>>,
+    pub transactions: Vec<Transaction>,
+}
+
+impl Statement {
+    /// The balance after each transaction; see [`running_balances`].
+    pub fn running_balances(&self) -> Option<Vec<RunningBalance<'_, Transaction>>> {
+        running_balances(
+            self.start_balance,
+            &self.transactions,
+            |t| t.posting_date.unwrap_or(t.date),
+            |t| t.printed_amount,
+        )
+    }
+}
+
+/// A transaction together with the balance right after it posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunningBalance<'a, T> {
+    pub transaction: &'a T,
+    /// The balance as the statement prints it, so the amount owed for credit
+    /// accounts.
+    pub balance: Money,
+}
+
+/// Walks `transactions` from `start_balance` in posting order, applying each
+/// printed amount. Transactions posted the same day keep the order they are
+/// printed in, section by section. Interest that a statement only reports as
+/// a period total is not included, so the last balance can fall short of the
+/// end balance by that much. Returns `None` if a balance overflows.
+pub fn running_balances<T>(
+    start_balance: Money,
+    transactions: &[T],
+    posting_date: impl Fn(&T) -> Date,
+    printed_amount: impl Fn(&T) -> Money,
+) -> Option<Vec<RunningBalance<'_, T>>> {
+    let mut in_posting_order: Vec<_> = transactions.iter().collect();
+    // A stable sort, so same-day transactions stay in printed order.
+    in_posting_order.sort_by_key(|t| posting_date(t));
+    let mut balance = start_balance;
+    in_posting_order
+        .into_iter()
+        .map(|transaction| {
+            balance = balance.checked_add(printed_amount(transaction))?;
+            Some(RunningBalance {
+                transaction,
+                balance,
+            })
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::money::{Currency, Rate};
+
+    #[test]
+    fn serde_round_trip() {
+        let date = Date::from_ymd_opt(2023, 5, 1).unwrap();
+        let transaction = Transaction {
+            kind: TransactionKind::Purchase,
+            fee_kind: None,
+            date,
+            posting_date: Some(date),
+            description: "CAFE DE FLORE PARIS".into(),
+            reference_number: None,
+            check_number: None,
+            section: None,
+            card_number: None,
+            amount: Money::usd(-4915),
+            printed_amount: Money::usd(4915),
+            foreign_exchange: Some(ForeignExchange {
+                original_cents: 4500,
+                currency: "EURO".into(),
+                currency_code: Currency::new("EUR"),
+                rate: Rate::new(10923, 4),
+            }),
+            foreign_transaction_fee: Some(1),
+        };
+        let statement = Statement {
+            issuer: Issuer::Chase,
+            account_kind: AccountKind::Credit,
+            account_number: "1234".into(),
+            start_date: date,
+            end_date: date,
+            start_balance: Money::usd(0),
+            end_balance: Money::usd(4915),
+            total_interest: None,
+            credit_terms: None,
+            year_to_date: None,
+            rewards: None,
+            daily_balances: Some(vec![(date, Money::usd(4915))]),
+            transactions: vec![transaction],
+        };
+        let json = serde_json::to_string(&statement).unwrap();
+        assert!(json.contains(r#""currency_code":"EUR""#));
+        assert_eq!(serde_json::from_str::<Statement>(&json).unwrap(), statement);
+    }
+}
@@ -1,5 +1,105 @@
+use std::io::Read;
 use std::path::Path;
 
-pub trait StatementFormat {
-    fn parse_file(path: &Path) -> Self;
+use chrono::Duration;
+
+use crate::error::{ExtractionError, StatementError};
+use crate::extract::TextExtractor;
+
+/// Settings for parsing statement text. `Default` suits the statements seen
+/// so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// How far outside the statement period a transaction printed without a
+    /// year may be dated before the parse fails. Defaults to 90 days.
+    pub date_tolerance: Duration,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            date_tolerance: Duration::days(90),
+        }
+    }
+}
+
+pub trait StatementFormat: Sized {
+    /// Parses text already extracted from a statement PDF, in the layout this
+    /// format's preferred extractor produces.
+    fn parse_text_with_options(text: &str, options: &ParseOptions) -> Result<Self, StatementError>;
+
+    /// Parses text already extracted from a statement PDF with the default
+    /// options.
+    fn parse_text(text: &str) -> Result<Self, StatementError> {
+        Self::parse_text_with_options(text, &ParseOptions::default())
+    }
+
+    /// The extractor whose output `parse_text` expects.
+    fn preferred_extractor() -> Box<dyn TextExtractor>;
+
+    /// Parses a statement PDF held in memory, extracting its text with
+    /// `extractor` instead of the preferred one.
+    fn parse_bytes_with(
+        bytes: &[u8],
+        extractor: &dyn TextExtractor,
+    ) -> Result<Self, StatementError> {
+        Self::parse_bytes_with_options(bytes, extractor, &ParseOptions::default())
+    }
+
+    /// Like `parse_bytes_with`, with `options` instead of the defaults. Pass
+    /// `Self::preferred_extractor().as_ref()` to extract as `parse_bytes` does.
+    fn parse_bytes_with_options(
+        bytes: &[u8],
+        extractor: &dyn TextExtractor,
+        options: &ParseOptions,
+    ) -> Result<Self, StatementError> {
+        Self::parse_text_with_options(&extractor.extract_bytes(bytes)?, options)
+    }
+
+    /// Parses the statement PDF at `path`, extracting its text with
+    /// `extractor` instead of the preferred one.
+    fn parse_file_with(path: &Path, extractor: &dyn TextExtractor) -> Result<Self, StatementError> {
+        Self::parse_file_with_options(path, extractor, &ParseOptions::default())
+    }
+
+    /// Like `parse_file_with`, with `options` instead of the defaults.
+    fn parse_file_with_options(
+        path: &Path,
+        extractor: &dyn TextExtractor,
+        options: &ParseOptions,
+    ) -> Result<Self, StatementError> {
+        Self::parse_text_with_options(&extractor.extract_file(path)?, options)
+    }
+
+    /// Parses a statement PDF held in memory.
+    fn parse_bytes(bytes: &[u8]) -> Result<Self, StatementError> {
+        Self::parse_bytes_with(bytes, Self::preferred_extractor().as_ref())
+    }
+
+    fn parse_file(path: &Path) -> Result<Self, StatementError> {
+        Self::parse_file_with(path, Self::preferred_extractor().as_ref())
+    }
+
+    /// Parses a statement PDF read to the end from `reader`.
+    fn parse_reader<R: Read>(reader: R) -> Result<Self, StatementError> {
+        Self::parse_reader_with_options(
+            reader,
+            Self::preferred_extractor().as_ref(),
+            &ParseOptions::default(),
+        )
+    }
+
+    /// Like `parse_reader`, extracting with `extractor` and parsing with
+    /// `options`.
+    fn parse_reader_with_options<R: Read>(
+        mut reader: R,
+        extractor: &dyn TextExtractor,
+        options: &ParseOptions,
+    ) -> Result<Self, StatementError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(ExtractionError::Io)?;
+        Self::parse_bytes_with_options(&bytes, extractor, options)
+    }
 }
@@ -0,0 +1,246 @@
+//! The "Totals Year-to-Date" table printed on credit card statements, and a
+//! check of it against the statements of that year.
+
+use chrono::Datelike;
+use nom::{
+    character::complete::{i32, multispace1},
+    sequence::separated_pair,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::common_parsers::*;
+use crate::error::{GrammarError, ReconciledTransaction, ReconciliationError};
+use crate::money::{Currency, Money};
+use crate::statement::{Statement, TransactionKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct YearToDate {
+    pub year: i32,
+    pub total_fees: Money,
+    pub total_interest: Money,
+}
+
+/// Reads "Total fees charged in <year>" and "Total interest charged in
+/// <year>". `None` unless the statement prints both.
+pub(crate) fn year_to_date(input: &str) -> Result<Option<YearToDate>, nom::Err<GrammarError<'_>>> {
+    let fees = labeled(
+        input,
+        "Total fees charged in",
+        separated_pair(i32, multispace1, dollar_amount),
+    )?;
+    let interest = labeled(
+        input,
+        "Total interest charged in",
+        separated_pair(i32, multispace1, dollar_amount),
+    )?;
+    Ok(match (fees, interest) {
+        (Some((year, total_fees)), Some((_, total_interest))) => Some(YearToDate {
+            year,
+            total_fees,
+            total_interest,
+        }),
+        _ => None,
+    })
+}
+
+fn mismatch(
+    section: String,
+    expected: Money,
+    computed: Option<Money>,
+    transactions: Vec<ReconciledTransaction>,
+) -> Result<(), ReconciliationError> {
+    match computed {
+        Some(computed) if computed == expected => Ok(()),
+        // An overflowing sum can't match; report it as zero.
+        computed => Err(ReconciliationError {
+            section,
+            expected,
+            computed: computed.unwrap_or(Money::zero(expected.currency())),
+            date: None,
+            transactions,
+        }),
+    }
+}
+
+/// Checks the year-to-date totals on the latest statement that prints them
+/// against the other statements for the same account. Fees and interest both
+/// count towards the year the statement period closed in, so a fee dated in
+/// December on a statement closing in January counts towards the new year.
+/// Pass every statement closing in the year. Does nothing if no statement has
+/// the totals.
+pub fn verify_year_to_date(statements: &[Statement]) -> Result<(), ReconciliationError> {
+    let Some((last, year_to_date)) = statements
+        .iter()
+        .filter_map(|s| Some((s, s.year_to_date.as_ref()?)))
+        .max_by_key(|(s, _)| s.end_date)
+    else {
+        return Ok(());
+    };
+    let year = year_to_date.year;
+    let account: Vec<_> = statements
+        .iter()
+        .filter(|s| {
+            s.issuer == last.issuer
+                && s.account_number == last.account_number
+                && s.end_date <= last.end_date
+                && s.end_date.year() == year
+        })
+        .collect();
+
+    let fees: Vec<_> = account
+        .iter()
+        .flat_map(|s| &s.transactions)
+        .filter(|t| t.kind == TransactionKind::Fee)
+        .collect();
+    mismatch(
+        format!("Total fees charged in {}", year),
+        year_to_date.total_fees,
+        Money::checked_sum(Currency::USD, fees.iter().map(|t| t.printed_amount)),
+        fees.iter()
+            .map(|t| ReconciledTransaction {
+                date: t.date,
+                description: t.description.clone(),
+                amount: t.printed_amount,
+            })
+            .collect(),
+    )?;
+
+    let interest: Vec<_> = account
+        .iter()
+        .filter_map(|s| Some((s.end_date, s.total_interest?)))
+        .collect();
+    mismatch(
+        format!("Total interest charged in {}", year),
+        year_to_date.total_interest,
+        Money::checked_sum(Currency::USD, interest.iter().map(|(_, amount)| *amount)),
+        interest
+            .iter()
+            .map(|&(date, amount)| ReconciledTransaction {
+                date,
+                description: "Interest charged for the period".into(),
+                amount,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::naive::NaiveDate as Date;
+
+    use super::*;
+    use crate::statement::{AccountKind, FeeKind, Issuer, Transaction};
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn fee(date: Date, cents: i64) -> Transaction {
+        Transaction {
+            kind: TransactionKind::Fee,
+            fee_kind: Some(FeeKind::Other),
+            date,
+            posting_date: None,
+            description: "LATE FEE".into(),
+            reference_number: None,
+            check_number: None,
+            section: None,
+            card_number: None,
+            amount: Money::usd(-cents),
+            printed_amount: Money::usd(cents),
+            foreign_exchange: None,
+            foreign_transaction_fee: None,
+        }
+    }
+
+    fn statement(
+        start_date: Date,
+        end_date: Date,
+        fees: Vec<Transaction>,
+        interest: i64,
+    ) -> Statement {
+        Statement {
+            issuer: Issuer::Chase,
+            account_kind: AccountKind::Credit,
+            account_number: "7890".into(),
+            start_date,
+            end_date,
+            start_balance: Money::usd(0),
+            end_balance: Money::usd(0),
+            total_interest: Some(Money::usd(interest)),
+            credit_terms: None,
+            year_to_date: None,
+            rewards: None,
+            daily_balances: None,
+            transactions: fees,
+        }
+    }
+
+    /// December, January and February statements, the last printing totals
+    /// for the year the January one closed in.
+    fn year(total_fees: i64, total_interest: i64) -> Vec<Statement> {
+        let mut february = statement(date(2023, 1, 15), date(2023, 2, 14), vec![], 250);
+        february.year_to_date = Some(YearToDate {
+            year: 2023,
+            total_fees: Money::usd(total_fees),
+            total_interest: Money::usd(total_interest),
+        });
+        vec![
+            statement(
+                date(2022, 11, 15),
+                date(2022, 12, 14),
+                vec![fee(date(2022, 12, 1), 4000)],
+                100,
+            ),
+            statement(
+                date(2022, 12, 15),
+                date(2023, 1, 14),
+                vec![fee(date(2022, 12, 20), 1000), fee(date(2023, 1, 3), 500)],
+                200,
+            ),
+            february,
+        ]
+    }
+
+    #[test]
+    fn reads_year_to_date_totals() {
+        let text = "Total fees charged in 2023   $15.00\n\
+                    Total interest charged in 2023   $4.50\n";
+        assert_eq!(
+            year_to_date(text).ok().flatten(),
+            Some(YearToDate {
+                year: 2023,
+                total_fees: Money::usd(1500),
+                total_interest: Money::usd(450),
+            })
+        );
+        assert_eq!(
+            year_to_date("Total fees charged in 2023 $15.00").ok(),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn totals_match_statements_closing_in_the_year() {
+        assert_eq!(verify_year_to_date(&year(1500, 450)), Ok(()));
+    }
+
+    #[test]
+    fn reports_fee_mismatch() {
+        let error = verify_year_to_date(&year(500, 450)).unwrap_err();
+        assert_eq!(error.section, "Total fees charged in 2023");
+        assert_eq!(error.expected, Money::usd(500));
+        assert_eq!(error.computed, Money::usd(1500));
+        assert_eq!(error.transactions.len(), 2);
+        assert_eq!(error.transactions[0].date, date(2022, 12, 20));
+    }
+
+    #[test]
+    fn reports_interest_mismatch() {
+        let error = verify_year_to_date(&year(1500, 550)).unwrap_err();
+        assert_eq!(error.section, "Total interest charged in 2023");
+        assert_eq!(error.computed, Money::usd(450));
+    }
+}